@@ -1,71 +1,93 @@
-use phf::phf_set;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::cmp::min;
 use std::collections::HashMap;
+use std::sync::Arc;
 
-use crate::constants::LETTERS_OCCURRENCE;
-
-pub static VOWELS: phf::Set<char> = phf_set! {
-    'A', 'E', 'I', 'O', 'U', 'Y', '?'
-};
-
-pub static CONSONANTS: phf::Set<char> = phf_set! {
-    'B', 'C', 'D', 'F', 'G', 'H', 'J', 'K', 'L', 'M', 'N',
-    'P', 'Q', 'R', 'S', 'T', 'V', 'W', 'X', 'Z', 'Y', '?'
-};
-
-fn augment_rack(rack: &mut HashMap<char, usize>, letters: Vec<char>) {
-    // Ajoute letters au rack
-    for letter in letters {
-        if let Some(count) = rack.get_mut(&letter) {
-            *count += 1;
-        } else {
-            rack.insert(letter, 1);
-        }
-    }
-}
+use crate::rack::{add_tiles, missing_tiles, remove_tiles};
+use crate::rules::{RuleSet, Tile};
 
 pub struct Bag {
-    bag: Vec<char>,
+    bag: Vec<Tile>,
     vowels_nb: usize,
     consonants_nb: usize,
+    rng: StdRng,
+    rules: Arc<RuleSet>,
 }
 
 impl Bag {
     pub fn new() -> Self {
-        // Crée un nouveau sac de lettres
-        let mut bag = Vec::new();
-        let mut vowels_nb = 0;
-        let mut consonants_nb = 0;
-        for (&letter, &count) in LETTERS_OCCURRENCE.entries() {
-            bag.extend(std::iter::repeat(letter).take(count));
-            if VOWELS.contains(&letter) {
-                vowels_nb += count;
-            };
-            if CONSONANTS.contains(&letter) {
-                consonants_nb += count;
+        // Crée un nouveau sac de lettres francophone avec un tirage aléatoire
+        Bag::with_rules(RuleSet::french(), None)
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        // Crée un nouveau sac de lettres francophone dont les tirages sont reproductibles
+        Bag::with_rules(RuleSet::french(), Some(seed))
+    }
+
+    pub fn with_rules(rules: Arc<RuleSet>, seed: Option<u64>) -> Self {
+        // Crée le sac de lettres de ces règles, aux tirages reproductibles si seed est donné
+        let mut tiles = Vec::new();
+        for (name, info) in &rules.tiles {
+            if let Some(letter) = rules.tile(name) {
+                tiles.extend(std::iter::repeat_n(letter, info.count));
             }
         }
+        Bag::from_tiles(rules, tiles, seed)
+    }
+
+    pub fn from_tiles(rules: Arc<RuleSet>, bag: Vec<Tile>, seed: Option<u64>) -> Self {
+        // Crée un sac contenant exactement ces jetons, comme les jetons encore invisibles
+        // d'une position analysée
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let vowels_nb = bag.iter().filter(|&&tile| rules.is_vowel(tile)).count();
+        let consonants_nb = bag.iter().filter(|&&tile| rules.is_consonant(tile)).count();
         Bag {
             bag,
             vowels_nb,
             consonants_nb,
+            rng,
+            rules,
         }
     }
 
-    fn draw(&mut self, nb_letters: usize) -> (Vec<char>, usize, usize) {
-        let mut rng = rand::thread_rng();
+    pub fn rules(&self) -> &Arc<RuleSet> {
+        &self.rules
+    }
+
+    pub fn len(&self) -> usize {
+        self.bag.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bag.is_empty()
+    }
+
+    pub fn fill(&mut self, rack: &mut HashMap<Tile, usize>, lim: usize) {
+        // Complète rack jusqu'à lim jetons au hasard, sans contrainte de voyelles ni de
+        // consonnes, comme dans une partie classique
+        let rack_len: usize = rack.values().sum();
+        let nb_letters = min(lim.saturating_sub(rack_len), self.bag.len());
+        let (drawn_letters, _, _) = self.draw(nb_letters);
+        add_tiles(rack, &drawn_letters);
+    }
+
+    fn draw(&mut self, nb_letters: usize) -> (Vec<Tile>, usize, usize) {
         let mut drawn_letters = Vec::new();
         let mut nb_v = 0;
         let mut nb_c = 0;
         for _ in 0..nb_letters {
-            let index = rng.gen_range(0..self.bag.len());
+            let index = self.rng.gen_range(0..self.bag.len());
             let letter = self.bag.remove(index);
-            if VOWELS.contains(&letter) {
+            if self.rules.is_vowel(letter) {
                 nb_v += 1;
                 self.vowels_nb -= 1;
             };
-            if CONSONANTS.contains(&letter) {
+            if self.rules.is_consonant(letter) {
                 nb_c += 1;
                 self.consonants_nb -= 1;
             }
@@ -74,30 +96,59 @@ impl Bag {
         (drawn_letters, nb_v, nb_c)
     }
 
-    fn discard(&mut self, rack: &mut HashMap<char, usize>, drawn_letters: Vec<char>) {
+    fn discard(&mut self, rack: &mut HashMap<Tile, usize>, drawn_letters: Vec<Tile>) {
         for letter in drawn_letters {
             self.bag.push(letter);
-            if VOWELS.contains(&letter) {
+            if self.rules.is_vowel(letter) {
                 self.vowels_nb += 1;
             };
-            if CONSONANTS.contains(&letter) {
+            if self.rules.is_consonant(letter) {
                 self.consonants_nb += 1;
             }
         }
-        for (letter, count) in rack.drain() {
-            self.bag.extend(std::iter::repeat(letter).take(count));
-            if VOWELS.contains(&letter) {
+        // Les lettres du rack sont remises dans un ordre fixe pour garder les tirages reproductibles
+        let mut rack_letters: Vec<(Tile, usize)> = rack.drain().collect();
+        rack_letters.sort_unstable();
+        for (letter, count) in rack_letters {
+            self.bag.extend(std::iter::repeat_n(letter, count));
+            if self.rules.is_vowel(letter) {
                 self.vowels_nb += count;
             };
-            if CONSONANTS.contains(&letter) {
+            if self.rules.is_consonant(letter) {
                 self.consonants_nb += count;
             }
         }
     }
 
+    pub fn exchange(
+        &mut self,
+        rack: &mut HashMap<Tile, usize>,
+        tiles: &[Tile],
+    ) -> Result<(), Vec<Tile>> {
+        // Pioche autant de jetons qu'on en rend, puis remet les jetons rendus dans le sac ;
+        // renvoie sans rien changer les jetons rendus qui ne sont pas sur le chevalet
+        let missing = missing_tiles(rack, tiles);
+        if !missing.is_empty() {
+            return Err(missing);
+        }
+        let (drawn_letters, _, _) = self.draw(min(tiles.len(), self.bag.len()));
+        remove_tiles(rack, tiles);
+        for &tile in tiles {
+            self.bag.push(tile);
+            if self.rules.is_vowel(tile) {
+                self.vowels_nb += 1;
+            };
+            if self.rules.is_consonant(tile) {
+                self.consonants_nb += 1;
+            }
+        }
+        add_tiles(rack, &drawn_letters);
+        Ok(())
+    }
+
     pub fn valid_draw(
         &mut self,
-        rack: &mut HashMap<char, usize>,
+        rack: &mut HashMap<Tile, usize>,
         lim: usize,
         min_vc: usize,
     ) -> bool {
@@ -107,10 +158,10 @@ impl Bag {
         let mut rack_vowels = 0;
         let mut rack_consonants = 0;
         for (letter, count) in rack.iter() {
-            if VOWELS.contains(letter) {
+            if self.rules.is_vowel(*letter) {
                 rack_vowels += count;
             }
-            if CONSONANTS.contains(letter) {
+            if self.rules.is_consonant(*letter) {
                 rack_consonants += count;
             }
             rack_len += count;
@@ -122,7 +173,7 @@ impl Bag {
         // Si il ne reste plus assez de lettres dans le sac, on les prend toutes
         if self.bag.len() <= lim - rack_len {
             let (drawn_letters, _, _) = self.draw(self.bag.len());
-            augment_rack(rack, drawn_letters);
+            add_tiles(rack, &drawn_letters);
             return true;
         }
         // Sinon on essaie d'effectuer un tirage valide
@@ -131,7 +182,7 @@ impl Bag {
         loop {
             let (drawn_letters, nb_v, nb_c) = self.draw(lim - rack_len);
             if nb_v + rack_vowels >= min_vowels && nb_c + rack_consonants >= min_consonants {
-                augment_rack(rack, drawn_letters);
+                add_tiles(rack, &drawn_letters);
                 return true;
             } else {
                 self.discard(rack, drawn_letters);
@@ -142,3 +193,9 @@ impl Bag {
         }
     }
 }
+
+impl Default for Bag {
+    fn default() -> Self {
+        Self::new()
+    }
+}
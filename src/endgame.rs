@@ -0,0 +1,395 @@
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+use crate::gaddag::Gaddag;
+use crate::grid::{Grid, Square};
+use crate::moves::Move;
+use crate::rules::Tile;
+use crate::solver::generate_solutions;
+
+// Réglages de la recherche de fin de partie
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EndgameConfig {
+    // Durée au-delà de laquelle la recherche s'arrête sur la dernière profondeur terminée
+    pub time_limit: Duration,
+    // Nombre maximal de coups examinés en profondeur, passes comprises
+    pub max_depth: usize,
+}
+
+impl Default for EndgameConfig {
+    fn default() -> Self {
+        EndgameConfig {
+            time_limit: Duration::from_secs(10),
+            max_depth: 30,
+        }
+    }
+}
+
+// Meilleure suite de coups trouvée pour le joueur au trait
+#[derive(Debug, Clone)]
+pub struct EndgameResult {
+    // Écart de points gagné sur l'adversaire jusqu'à la fin, bonus et pénalités compris
+    pub spread: i64,
+    // Variante principale, en alternant les joueurs ; None est une passe
+    pub pv: Vec<Option<Move>>,
+    // Profondeur de la dernière recherche terminée
+    pub depth: usize,
+    // Vrai si la recherche a été menée jusqu'à la fin de la partie sur toutes les branches
+    pub exact: bool,
+    pub nodes: usize,
+}
+
+// Position d'une fin de partie : la grille, les deux chevalets, le premier étant celui du
+// joueur au trait, et le nombre de passes consécutives
+#[derive(Clone)]
+struct State {
+    grid: Grid,
+    racks: [HashMap<Tile, usize>; 2],
+    passes: usize,
+}
+
+impl State {
+    fn key(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for square in self.grid.squares.iter().flatten() {
+            match square {
+                Square::Letter(letter) => letter.hash(&mut hasher),
+                _ => 0u8.hash(&mut hasher),
+            }
+        }
+        for rack in &self.racks {
+            let mut tiles: Vec<(&Tile, &usize)> = rack.iter().collect();
+            tiles.sort_unstable();
+            tiles.hash(&mut hasher);
+        }
+        self.passes.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn rack_value(&self, player: usize) -> i64 {
+        self.grid.rules().rack_value(&self.racks[player]) as i64
+    }
+
+    fn play(&self, action: &Option<Move>, gaddag: &Gaddag) -> State {
+        // Position après le coup du joueur au trait, l'adversaire prenant le trait
+        let mut next = self.clone();
+        match action {
+            Some(mv) => {
+                next.grid.place(mv, gaddag);
+                next.racks[0] = mv.leave.clone();
+                next.passes = 0;
+            }
+            None => next.passes += 1,
+        }
+        next.racks.swap(0, 1);
+        next
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+struct Entry {
+    value: i64,
+    bound: Bound,
+    // Profondeur cherchée sous cette position, sans limite si complete
+    depth: usize,
+    complete: bool,
+    best: Option<Option<Move>>,
+}
+
+// Valeur d'une position vue par le joueur au trait, et si elle ne dépend d'aucune coupure
+// de profondeur
+struct Outcome {
+    value: i64,
+    complete: bool,
+}
+
+struct Search<'a> {
+    gaddag: &'a Gaddag,
+    table: HashMap<u64, Entry>,
+    deadline: Instant,
+    nodes: usize,
+}
+
+impl Search<'_> {
+    fn actions(&self, state: &State, key: u64) -> Vec<Option<Move>> {
+        // Coups possibles, ceux qui vident le chevalet puis les mieux payés d'abord, la passe
+        // en dernier ; le meilleur coup déjà trouvé pour cette position passe devant
+        let mut moves = generate_solutions(&state.grid, &state.racks[0], self.gaddag);
+        moves.sort_by_key(|mv| (!mv.leave.is_empty(), Reverse(mv.score.total())));
+        let mut actions: Vec<Option<Move>> = moves.into_iter().map(Some).collect();
+        actions.push(None);
+        if let Some(Entry {
+            best: Some(best), ..
+        }) = self.table.get(&key)
+        {
+            if let Some(index) = actions.iter().position(|action| action == best) {
+                let best = actions.remove(index);
+                actions.insert(0, best);
+            }
+        }
+        actions
+    }
+
+    fn negamax(
+        &mut self,
+        state: &State,
+        depth: usize,
+        mut alpha: i64,
+        beta: i64,
+    ) -> Option<Outcome> {
+        // Écart que le joueur au trait peut encore gagner, None si le temps est écoulé
+        if Instant::now() >= self.deadline {
+            return None;
+        }
+        self.nodes += 1;
+        let key = state.key();
+        let original_alpha = alpha;
+        if let Some(entry) = self.table.get(&key) {
+            if entry.complete || entry.depth >= depth {
+                let outcome = Outcome {
+                    value: entry.value,
+                    complete: entry.complete,
+                };
+                match entry.bound {
+                    Bound::Exact => return Some(outcome),
+                    Bound::Lower if entry.value >= beta => return Some(outcome),
+                    Bound::Upper if entry.value <= alpha => return Some(outcome),
+                    _ => {}
+                }
+            }
+        }
+        if depth == 0 {
+            // Sans aller plus loin, on suppose que chacun garde ses jetons
+            return Some(Outcome {
+                value: state.rack_value(1) - state.rack_value(0),
+                complete: false,
+            });
+        }
+        let mut best_value = i64::MIN;
+        let mut best_action = None;
+        let mut complete = true;
+        for action in self.actions(state, key) {
+            let outcome = match &action {
+                // Finir rapporte deux fois la valeur des jetons de l'adversaire, qu'il perd
+                Some(mv) if mv.leave.is_empty() => Outcome {
+                    value: mv.score.total() as i64 + 2 * state.rack_value(1),
+                    complete: true,
+                },
+                // Deux passes de suite terminent la partie, chacun perdant ses jetons : la règle
+                // générale attend six tours sans points, mais sac vide, après une passe de
+                // chacun la même position revient au même joueur, qui avait déjà préféré passer
+                None if state.passes == 1 => Outcome {
+                    value: state.rack_value(1) - state.rack_value(0),
+                    complete: true,
+                },
+                _ => {
+                    // La fenêtre de l'adversaire est décalée des points marqués par le coup
+                    let score = action.as_ref().map_or(0, |mv| mv.score.total() as i64);
+                    let next = state.play(&action, self.gaddag);
+                    let reply = self.negamax(
+                        &next,
+                        depth - 1,
+                        score.saturating_sub(beta),
+                        score.saturating_sub(alpha),
+                    )?;
+                    Outcome {
+                        value: score - reply.value,
+                        complete: reply.complete,
+                    }
+                }
+            };
+            complete &= outcome.complete;
+            if outcome.value > best_value {
+                best_value = outcome.value;
+                best_action = Some(action);
+            }
+            alpha = alpha.max(best_value);
+            if alpha >= beta {
+                break;
+            }
+        }
+        let bound = if best_value <= original_alpha {
+            Bound::Upper
+        } else if best_value >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.table.insert(
+            key,
+            Entry {
+                value: best_value,
+                bound,
+                depth,
+                complete,
+                best: best_action,
+            },
+        );
+        Some(Outcome {
+            value: best_value,
+            complete,
+        })
+    }
+
+    fn principal_variation(&self, state: &State) -> Vec<Option<Move>> {
+        // Suit les meilleurs coups de la table jusqu'à la fin de la partie
+        let mut pv = Vec::new();
+        let mut state = state.clone();
+        while let Some(Entry {
+            best: Some(action), ..
+        }) = self.table.get(&state.key())
+        {
+            pv.push(action.clone());
+            let ends = match action {
+                Some(mv) => mv.leave.is_empty(),
+                None => state.passes == 1,
+            };
+            if ends {
+                break;
+            }
+            state = state.play(action, self.gaddag);
+        }
+        pv
+    }
+}
+
+pub fn solve_endgame(
+    grid: &Grid,
+    rack: &HashMap<Tile, usize>,
+    opponent: &HashMap<Tile, usize>,
+    gaddag: &Gaddag,
+    config: &EndgameConfig,
+) -> EndgameResult {
+    // Cherche par alpha-bêta, en approfondissant petit à petit, la suite de coups qui donne le
+    // meilleur écart final quand le sac est vide et que les deux chevalets sont connus
+    let state = State {
+        grid: grid.clone(),
+        racks: [rack.clone(), opponent.clone()],
+        passes: 0,
+    };
+    let mut search = Search {
+        gaddag,
+        table: HashMap::new(),
+        deadline: Instant::now() + config.time_limit,
+        nodes: 0,
+    };
+    let mut result = EndgameResult {
+        spread: 0,
+        pv: Vec::new(),
+        depth: 0,
+        exact: false,
+        nodes: 0,
+    };
+    for depth in 1..=config.max_depth.max(1) {
+        let Some(outcome) = search.negamax(&state, depth, -i64::MAX, i64::MAX) else {
+            break;
+        };
+        result.spread = outcome.value;
+        result.pv = search.principal_variation(&state);
+        result.depth = depth;
+        result.exact = outcome.complete;
+        if outcome.complete {
+            break;
+        }
+    }
+    result.nodes = search.nodes;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::Direction;
+    use crate::rules::RuleSet;
+
+    fn test_gaddag() -> Gaddag {
+        Gaddag::from_words(
+            &["SAMA", "SAMAS", "AS", "ZA", "ZAS"],
+            "test",
+            &RuleSet::french(),
+        )
+    }
+
+    fn solve(rack: &str, opponent: &str, config: &EndgameConfig) -> EndgameResult {
+        // Fin de partie sur une grille avec SAMA en H8
+        let rules = RuleSet::french();
+        let gaddag = test_gaddag();
+        let mut grid = Grid::new();
+        grid.generate_grid();
+        grid.play(&Move::new(7, 7, Direction::Horizontal, "SAMA"), &gaddag)
+            .unwrap();
+        let rack = rules.parse_rack(rack).unwrap();
+        let opponent = rules.parse_rack(opponent).unwrap();
+        solve_endgame(&grid, &rack, &opponent, &gaddag, config)
+    }
+
+    fn line(pv: &[Option<Move>]) -> Vec<String> {
+        pv.iter()
+            .map(|action| match action {
+                Some(mv) => format!("{} {} {}", mv.coordinates(), mv.word, mv.score.total()),
+                None => "pass".to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn going_out_scores_twice_the_opponent_rack() {
+        // ZA vide le chevalet : 21 points plus deux fois K et E
+        let result = solve("Z", "KE", &EndgameConfig::default());
+        assert_eq!(line(&result.pv), ["9G ZA 21"]);
+        assert_eq!(result.spread, 21 + 2 * 11);
+        assert!(result.exact);
+    }
+
+    #[test]
+    fn best_line_blocks_before_scoring() {
+        // Jouer ZAS tout de suite laisse SAMAS à l'adversaire ; on le prend d'abord, puis
+        // l'adversaire, bloqué avec son K, passe pendant qu'on finit
+        let result = solve("ZSS", "KS", &EndgameConfig::default());
+        assert_eq!(
+            line(&result.pv),
+            ["H8 SAMAS 7", "9H AS 3", "9G ZAS 22", "pass", "11H AS 2"]
+        );
+        assert_eq!(result.spread, 7 - 3 + 22 + 2 + 2 * 10);
+        assert!(result.exact);
+        assert_eq!(result.depth, 5);
+    }
+
+    #[test]
+    fn two_passes_end_the_game() {
+        // Personne ne peut jouer : chacun perd la valeur de ses jetons
+        let result = solve("K", "E", &EndgameConfig::default());
+        assert_eq!(line(&result.pv), ["pass", "pass"]);
+        assert_eq!(result.spread, 1 - 10);
+        assert!(result.exact);
+    }
+
+    #[test]
+    fn a_cut_search_is_not_exact() {
+        let expired = EndgameConfig {
+            time_limit: Duration::ZERO,
+            ..EndgameConfig::default()
+        };
+        let result = solve("ZSS", "KS", &expired);
+        assert!(!result.exact);
+        assert_eq!(result.depth, 0);
+        assert!(result.pv.is_empty());
+        let shallow = EndgameConfig {
+            max_depth: 2,
+            ..EndgameConfig::default()
+        };
+        let result = solve("ZSS", "KS", &shallow);
+        assert!(!result.exact);
+        assert_eq!(result.depth, 2);
+        assert!(!result.pv.is_empty());
+    }
+}
@@ -0,0 +1,263 @@
+use std::fmt::{self, Write};
+
+use serde_json::{json, Map, Value};
+
+use crate::grid::{Grid, Square};
+use crate::moves::Direction;
+use crate::rules::{square_marker, RuleSet};
+use crate::scoring::square_name;
+
+// Taille d'une case et marge réservée aux coordonnées dans l'image SVG, en pixels
+const CELL: usize = 36;
+const MARGIN: usize = 24;
+
+pub fn square_text(rules: &RuleSet, square: Square) -> String {
+    // Texte d'une case dans l'export texte : la lettre, en minuscules pour un joker,
+    // ou le marqueur de la case vide
+    match square {
+        Square::Letter(letter) => rules.letter_text(letter),
+        _ => square_marker(square).to_string(),
+    }
+}
+
+fn cell_name(i: usize, j: usize) -> String {
+    Grid::pos_to_ref((i, j), Direction::Horizontal)
+}
+
+pub fn grid_to_text(grid: &Grid) -> String {
+    // Une ligne par rangée, les cases séparées par des espaces ; le texte peut être relu
+    // par import::grid_from_text
+    let mut text = String::new();
+    for row in &grid.squares {
+        let cells: Vec<String> = row.iter().map(|&s| square_text(grid.rules(), s)).collect();
+        text.push_str(&cells.join(" "));
+        text.push('\n');
+    }
+    text
+}
+
+pub fn grid_to_json(grid: &Grid) -> Value {
+    // État complet de la grille : jetons, cases bonus encore libres, ancres et
+    // lettres autorisées par les mots croisés avec leur score
+    let mut tiles = Vec::new();
+    let mut premiums = Vec::new();
+    let mut anchors = Vec::new();
+    let mut crosswords = Vec::new();
+    for i in 0..grid.size() {
+        for j in 0..grid.size() {
+            match grid.squares[i][j] {
+                Square::Letter(letter) => tiles.push(json!({
+                    "square": cell_name(i, j),
+                    "row": i,
+                    "col": j,
+                    "letter": grid.rules().name(letter.tile),
+                    "blank": letter.blank,
+                    "value": grid.rules().letter_value(letter),
+                })),
+                square => {
+                    if let Some(name) = square_name(square) {
+                        premiums.push(json!({
+                            "square": cell_name(i, j),
+                            "row": i,
+                            "col": j,
+                            "premium": name,
+                        }));
+                    }
+                }
+            }
+            if grid.anchors[i][j] {
+                anchors.push(Value::String(cell_name(i, j)));
+            }
+            if let Some(map) = &grid.crosswords[i][j] {
+                let letters: Map<String, Value> = map
+                    .iter()
+                    .map(|(&tile, score)| (grid.rules().name(tile).to_string(), json!(score)))
+                    .collect();
+                crosswords.push(json!({
+                    "square": cell_name(i, j),
+                    "row": i,
+                    "col": j,
+                    "letters": letters,
+                }));
+            }
+        }
+    }
+    let rows: Vec<Vec<String>> = grid
+        .squares
+        .iter()
+        .map(|row| row.iter().map(|&s| square_text(grid.rules(), s)).collect())
+        .collect();
+    json!({
+        "size": grid.size(),
+        "rows": rows,
+        "tiles": tiles,
+        "premiums": premiums,
+        "anchors": anchors,
+        "crosswords": crosswords,
+    })
+}
+
+fn square_colours(square: Square) -> (&'static str, &'static str) {
+    // Couleur de fond d'une case libre et couleur de son libellé
+    match square {
+        Square::LCD => ("#a9d8f2", "#1a1a1a"),
+        Square::LCT => ("#3b8fd0", "#ffffff"),
+        Square::LCQ => ("#1d4f91", "#ffffff"),
+        Square::MCD => ("#f6b8c0", "#1a1a1a"),
+        Square::MCT => ("#e04a3f", "#ffffff"),
+        Square::MCQ => ("#8e1b2f", "#ffffff"),
+        _ => ("#1f7a4d", "#ffffff"),
+    }
+}
+
+pub fn grid_to_svg(grid: &Grid) -> String {
+    // Dessine la grille : cases bonus colorées, rangées nommées par des lettres à gauche,
+    // colonnes numérotées en haut, jokers sur fond jaune avec la lettre en rouge et sans valeur
+    let mut svg = String::new();
+    write_svg(grid, &mut svg).expect("writing to a String cannot fail");
+    svg
+}
+
+fn write_svg(grid: &Grid, svg: &mut String) -> fmt::Result {
+    let size = MARGIN + grid.size() * CELL + 1;
+    writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{size}\" height=\"{size}\" \
+         viewBox=\"0 0 {size} {size}\" font-family=\"Helvetica, Arial, sans-serif\">"
+    )?;
+    writeln!(
+        svg,
+        "<rect width=\"{size}\" height=\"{size}\" fill=\"#ffffff\"/>"
+    )?;
+    for k in 0..grid.size() {
+        let centre = MARGIN + k * CELL + CELL / 2;
+        writeln!(
+            svg,
+            "<text x=\"{centre}\" y=\"{}\" font-size=\"12\" text-anchor=\"middle\">{}</text>",
+            MARGIN - 8,
+            k + 1
+        )?;
+        writeln!(
+            svg,
+            "<text x=\"{}\" y=\"{}\" font-size=\"12\" text-anchor=\"middle\">{}</text>",
+            MARGIN / 2,
+            centre + 4,
+            (b'A' + k as u8) as char
+        )?;
+    }
+    for (i, row) in grid.squares.iter().enumerate() {
+        for (j, &square) in row.iter().enumerate() {
+            let x = MARGIN + j * CELL;
+            let y = MARGIN + i * CELL;
+            let fill = match square {
+                Square::Letter(letter) if letter.blank => "#ffe27a",
+                Square::Letter(_) => "#f2dcb0",
+                _ => square_colours(square).0,
+            };
+            writeln!(
+                svg,
+                "<rect x=\"{x}\" y=\"{y}\" width=\"{CELL}\" height=\"{CELL}\" fill=\"{fill}\" \
+                 stroke=\"#ffffff\" stroke-width=\"1\"/>"
+            )?;
+            match square {
+                Square::Letter(letter) => {
+                    let colour = if letter.blank { "#c0392b" } else { "#222222" };
+                    // Les lettres de plusieurs caractères, comme CH, sont écrites plus petit
+                    let name = grid.rules().name(letter.tile);
+                    let font_size = match name.chars().count() {
+                        1 => 22,
+                        2 => 16,
+                        _ => 12,
+                    };
+                    writeln!(
+                        svg,
+                        "<text x=\"{}\" y=\"{}\" font-size=\"{font_size}\" font-weight=\"bold\" \
+                         text-anchor=\"middle\" fill=\"{colour}\">{}</text>",
+                        x + CELL / 2,
+                        y + CELL / 2 + font_size * 4 / 11,
+                        name
+                    )?;
+                    if !letter.blank {
+                        writeln!(
+                            svg,
+                            "<text x=\"{}\" y=\"{}\" font-size=\"9\" text-anchor=\"end\" \
+                             fill=\"#222222\">{}</text>",
+                            x + CELL - 3,
+                            y + CELL - 3,
+                            grid.rules().value(letter.tile)
+                        )?;
+                    }
+                }
+                _ => {
+                    if let Some(name) = square_name(square) {
+                        writeln!(
+                            svg,
+                            "<text x=\"{}\" y=\"{}\" font-size=\"10\" text-anchor=\"middle\" \
+                             fill=\"{}\">{name}</text>",
+                            x + CELL / 2,
+                            y + CELL / 2 + 4,
+                            square_colours(square).1
+                        )?;
+                    }
+                }
+            }
+        }
+    }
+    writeln!(svg, "</svg>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gaddag::Gaddag;
+    use crate::import::grid_from_text;
+    use crate::moves::Move;
+    use crate::rules::RuleSet;
+
+    fn test_gaddag() -> Gaddag {
+        Gaddag::from_words(&["SAMA", "MAS", "AS", "SA"], "test", &RuleSet::french())
+    }
+
+    fn test_grid(gaddag: &Gaddag) -> Grid {
+        // SAMA en H8 avec un joker pour le premier A, puis MAS en 11G
+        let mut grid = Grid::new();
+        grid.generate_grid();
+        grid.play(&Move::new(7, 7, Direction::Horizontal, "SaMA"), gaddag)
+            .unwrap();
+        grid.play(&Move::new(6, 10, Direction::Vertical, "MAS"), gaddag)
+            .unwrap();
+        grid
+    }
+
+    #[test]
+    fn text_export_matches_the_snapshot() {
+        let gaddag = test_gaddag();
+        let grid = test_grid(&gaddag);
+        assert_eq!(grid_to_text(&grid), include_str!("snapshots/board.txt"));
+    }
+
+    #[test]
+    fn json_export_matches_the_snapshot() {
+        let gaddag = test_gaddag();
+        let grid = test_grid(&gaddag);
+        let expected: Value = serde_json::from_str(include_str!("snapshots/board.json")).unwrap();
+        assert_eq!(grid_to_json(&grid), expected);
+    }
+
+    #[test]
+    fn svg_export_matches_the_snapshot() {
+        let gaddag = test_gaddag();
+        let grid = test_grid(&gaddag);
+        assert_eq!(grid_to_svg(&grid), include_str!("snapshots/board.svg"));
+    }
+
+    #[test]
+    fn text_export_reads_back_to_the_same_board() {
+        let gaddag = test_gaddag();
+        let grid = test_grid(&gaddag);
+        let read = grid_from_text(&grid_to_text(&grid), &RuleSet::french(), &gaddag).unwrap();
+        assert_eq!(read.squares, grid.squares);
+        assert_eq!(read.anchors, grid.anchors);
+        assert_eq!(read.crosswords, grid.crosswords);
+    }
+}
@@ -1,103 +1,685 @@
-use std::cell::RefCell;
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::{self, BufRead};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
 use std::path::Path;
-use std::rc::Rc;
 
-pub type GaddagNode = Rc<RefCell<Gaddag>>;
+use crate::rules::{RuleSet, Tile};
 
+// Séparateur entre le préfixe renversé et le suffixe d'un mot, hors de l'alphabet des règles
+pub const SEPARATOR: Tile = Tile::MAX - 1;
+
+// En-tête des dictionnaires précompilés
+const MAGIC: &[u8; 6] = b"GADDAG";
+const FORMAT_VERSION: u16 = 2;
+
+// Index d'un noeud dans le gaddag
+pub type NodeId = u32;
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    first_edge: u32,
+    edges_nb: u8,
+    is_word: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    letter: u8,
+    child: NodeId,
+}
+
+// Gaddag minimisé : les suffixes communs sont fusionnés et les noeuds sont stockés à plat.
+// Les arêtes d'un noeud sont contiguës et triées par lettre, le noeud 0 est la racine.
+// Une arête porte 0 pour le séparateur et k + 1 pour le jeton k de l'alphabet des règles.
 #[derive(Debug)]
 pub struct Gaddag {
-    pub is_word: bool,
-    pub children: HashMap<char, GaddagNode>,
+    source: String,
+    alphabet: Vec<String>,
+    words_nb: u32,
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+    // Lignes de la liste de mots écartées à la construction, vide pour un fichier précompilé
+    rejected: Vec<RejectedLine>,
 }
 
-impl Gaddag {
-    fn new() -> GaddagNode {
-        Rc::new(RefCell::new(Gaddag {
+// Ligne d'une liste de mots qui ne s'écrit pas avec l'alphabet des règles
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectedLine {
+    pub line: usize,
+    pub text: String,
+    pub c: char,
+}
+
+impl fmt::Display for RejectedLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}: '{}' contains '{}', which is not in the alphabet",
+            self.line,
+            self.text.trim(),
+            self.c
+        )
+    }
+}
+
+// Le dictionnaire est immuable une fois construit et peut être partagé entre threads
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Gaddag>();
+};
+
+// Noeud utilisé pendant la construction, avant la mise à plat
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct BuildNode {
+    is_word: bool,
+    children: Vec<(u8, usize)>,
+}
+
+struct Builder {
+    nodes: Vec<BuildNode>,
+    register: HashMap<BuildNode, usize>,
+    // Noeuds fusionnés dont l'emplacement peut être réutilisé
+    free: Vec<usize>,
+    // Chemin de la dernière séquence insérée, pas encore minimisé
+    unchecked: Vec<(usize, u8, usize)>,
+}
+
+impl Builder {
+    fn new() -> Self {
+        Builder {
+            nodes: vec![BuildNode {
+                is_word: false,
+                children: Vec::new(),
+            }],
+            register: HashMap::new(),
+            free: Vec::new(),
+            unchecked: Vec::new(),
+        }
+    }
+
+    fn minimize(&mut self, down_to: usize) {
+        // Remplace les noeuds du chemin par un noeud équivalent déjà enregistré s'il existe
+        while self.unchecked.len() > down_to {
+            let (parent, letter, child) = self.unchecked.pop().unwrap();
+            let node = self.nodes[child].clone();
+            match self.register.get(&node) {
+                Some(&existing) => {
+                    let edge = self.nodes[parent].children.last_mut().unwrap();
+                    debug_assert_eq!(edge.0, letter);
+                    edge.1 = existing;
+                    self.free.push(child);
+                }
+                None => {
+                    self.register.insert(node, child);
+                }
+            }
+        }
+    }
+
+    fn new_node(&mut self) -> usize {
+        let empty = BuildNode {
             is_word: false,
-            children: HashMap::new(),
-        }))
+            children: Vec::new(),
+        };
+        match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = empty;
+                index
+            }
+            None => {
+                self.nodes.push(empty);
+                self.nodes.len() - 1
+            }
+        }
     }
 
-    fn insert_into_gaddag(word: &[char], gaddag: GaddagNode) {
-        // Insère la séquence word dans le gaddag
-        let mut gaddag = gaddag.borrow_mut();
-        if word.is_empty() {
-            gaddag.is_word = true;
-        } else {
-            let first_char = word[0];
-            let rest_word = &word[1..];
-            let child = gaddag
-                .children
-                .entry(first_char)
-                .or_insert_with(|| Gaddag::new());
-            Gaddag::insert_into_gaddag(rest_word, Rc::clone(child));
-        }
-    }
-
-    fn generate_permutations(word: &str, gaddag: GaddagNode) {
-        // Génère toutes les permutations de word à insérer dans le gaddag
-        for i in 0..word.len() {
-            let mut w: Vec<char> = Vec::new();
-            for j in (0..=i).rev() {
-                w.push(word.chars().nth(j).unwrap());
+    fn insert(&mut self, sequence: &[u8], previous: &[u8]) {
+        // Insère une séquence, les séquences devant arriver triées (algorithme de Daciuk)
+        let common = sequence
+            .iter()
+            .zip(previous)
+            .take_while(|(a, b)| a == b)
+            .count()
+            .min(self.unchecked.len());
+        self.minimize(common);
+        let mut node = match self.unchecked.last() {
+            Some(&(_, _, child)) => child,
+            None => 0,
+        };
+        for &letter in &sequence[common..] {
+            let child = self.new_node();
+            self.nodes[node].children.push((letter, child));
+            self.unchecked.push((node, letter, child));
+            node = child;
+        }
+        self.nodes[node].is_word = true;
+    }
+
+    fn finish(mut self) -> (Vec<Node>, Vec<Edge>) {
+        // Minimise le dernier chemin puis renumérote les noeuds accessibles depuis la racine
+        self.minimize(0);
+        let mut ids: HashMap<usize, NodeId> = HashMap::new();
+        let mut order = vec![0];
+        ids.insert(0, 0);
+        let mut k = 0;
+        while k < order.len() {
+            for &(_, child) in &self.nodes[order[k]].children {
+                if let std::collections::hash_map::Entry::Vacant(entry) = ids.entry(child) {
+                    entry.insert(order.len() as NodeId);
+                    order.push(child);
+                }
             }
-            w.push('!');
-            for j in (i + 1)..word.len() {
-                w.push(word.chars().nth(j).unwrap());
+            k += 1;
+        }
+        let mut nodes = Vec::with_capacity(order.len());
+        let mut edges = Vec::new();
+        for &old in &order {
+            let build_node = &self.nodes[old];
+            nodes.push(Node {
+                first_edge: edges.len() as u32,
+                edges_nb: build_node.children.len() as u8,
+                is_word: build_node.is_word,
+            });
+            for &(letter, child) in &build_node.children {
+                edges.push(Edge {
+                    letter,
+                    child: ids[&child],
+                });
             }
-            Gaddag::insert_into_gaddag(&w, Rc::clone(&gaddag));
         }
+        (nodes, edges)
     }
+}
 
-    pub fn follow_path(node: &GaddagNode, path: &str) -> Option<GaddagNode> {
-        // Retourne le noeud en partant de node et en suivant path
-        let mut current_node = Rc::clone(node);
-        for c in path.chars() {
-            let next_node = current_node.borrow().children.get(&c).cloned();
-            match next_node {
-                Some(next_node) => current_node = next_node,
-                None => return None,
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.offset + n > self.data.len() {
+            return Err(invalid_data("truncated dictionary file"));
+        }
+        let slice = &self.data[self.offset..self.offset + n];
+        self.offset += n;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.u16()? as usize;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| invalid_data("invalid UTF-8 in dictionary header"))
+    }
+}
+
+impl Gaddag {
+    fn with_alphabet(source: &str, alphabet: Vec<String>) -> Self {
+        // Crée un gaddag vide pour les lettres de l'alphabet des règles
+        Gaddag {
+            source: source.to_string(),
+            alphabet,
+            words_nb: 0,
+            nodes: vec![Node {
+                first_edge: 0,
+                edges_nb: 0,
+                is_word: false,
+            }],
+            edges: Vec::new(),
+            rejected: Vec::new(),
+        }
+    }
+
+    pub fn from_words<S: AsRef<str>>(words: &[S], source: &str, rules: &RuleSet) -> Self {
+        // Construit le gaddag minimisé contenant tous les mots, une ligne brute par mot
+        // normalisée puis découpée en jetons des règles ; les lignes vides sont ignorées et
+        // celles qui ne s'écrivent pas avec l'alphabet sont écartées avec leur numéro
+        let mut gaddag = Gaddag::with_alphabet(source, rules.alphabet.clone());
+        // Génère toutes les permutations de chaque mot puis les trie pour la construction
+        let mut sequences: Vec<Vec<u8>> = Vec::new();
+        for (line, word) in words.iter().enumerate() {
+            let tiles = match rules.normalize_word(word.as_ref()) {
+                Ok(tiles) => tiles,
+                Err(c) => {
+                    gaddag.rejected.push(RejectedLine {
+                        line: line + 1,
+                        text: word.as_ref().to_string(),
+                        c,
+                    });
+                    continue;
+                }
+            };
+            let word: Vec<u8> = tiles.iter().map(|&tile| tile + 1).collect();
+            for i in 0..word.len() {
+                let mut sequence = Vec::with_capacity(word.len() + 1);
+                sequence.extend(word[..=i].iter().rev());
+                sequence.push(0);
+                sequence.extend(&word[i + 1..]);
+                sequences.push(sequence);
             }
         }
-        Some(current_node)
+        sequences.sort_unstable();
+        sequences.dedup();
+        // Chaque mot distinct a une seule permutation avec le séparateur en deuxième position,
+        // ce qui compte les doublons de la liste une seule fois
+        gaddag.words_nb = sequences.iter().filter(|sequence| sequence[1] == 0).count() as u32;
+        let mut builder = Builder::new();
+        let mut previous: &[u8] = &[];
+        for sequence in &sequences {
+            builder.insert(sequence, previous);
+            previous = sequence;
+        }
+        let (nodes, edges) = builder.finish();
+        gaddag.nodes = nodes;
+        gaddag.edges = edges;
+        gaddag
+    }
+
+    pub fn read_words_from_file(filename: &str, rules: &RuleSet) -> io::Result<Self> {
+        // Crée un nouveau gaddag qui contient tous les mots présents dans filename
+        let bytes = fs::read(Path::new(filename))?;
+        // Chaque ligne correspond à un mot ; un octet hors UTF-8, fréquent dans les extractions
+        // de PDF, devient un caractère de remplacement et sa ligne est écartée
+        let words: Vec<String> = bytes
+            .split(|&byte| byte == b'\n')
+            .map(|line| String::from_utf8_lossy(line).into_owned())
+            .collect();
+        let source = Path::new(filename)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| filename.to_string());
+        Ok(Gaddag::from_words(&words, &source, rules))
+    }
+
+    pub fn save(&self, filename: &str) -> io::Result<()> {
+        // Écrit le gaddag dans un fichier binaire précompilé
+        let mut out = io::BufWriter::new(File::create(filename)?);
+        out.write_all(MAGIC)?;
+        out.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        // L'alphabet est écrit avec le nombre de lettres puis chaque lettre
+        out.write_all(&(self.source.len() as u16).to_le_bytes())?;
+        out.write_all(self.source.as_bytes())?;
+        out.write_all(&(self.alphabet.len() as u16).to_le_bytes())?;
+        for letter in &self.alphabet {
+            out.write_all(&(letter.len() as u16).to_le_bytes())?;
+            out.write_all(letter.as_bytes())?;
+        }
+        out.write_all(&self.words_nb.to_le_bytes())?;
+        out.write_all(&(self.nodes.len() as u32).to_le_bytes())?;
+        out.write_all(&(self.edges.len() as u32).to_le_bytes())?;
+        for node in &self.nodes {
+            out.write_all(&node.first_edge.to_le_bytes())?;
+            out.write_all(&[node.edges_nb, node.is_word as u8])?;
+        }
+        for edge in &self.edges {
+            out.write_all(&edge.child.to_le_bytes())?;
+            out.write_all(&[edge.letter])?;
+        }
+        out.flush()
+    }
+
+    pub fn load(filename: &str, rules: &RuleSet) -> io::Result<Self> {
+        // Charge un gaddag précompilé par save, avec le même alphabet que les règles
+        let data = fs::read(filename)?;
+        let mut reader = Reader {
+            data: &data,
+            offset: 0,
+        };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(invalid_data("not a precompiled dictionary"));
+        }
+        let version = reader.u16()?;
+        if version != FORMAT_VERSION {
+            return Err(invalid_data(&format!(
+                "unsupported dictionary version {} (expected {})",
+                version, FORMAT_VERSION
+            )));
+        }
+        let source = reader.string()?;
+        let letters_nb = reader.u16()?;
+        let alphabet = (0..letters_nb)
+            .map(|_| reader.string())
+            .collect::<io::Result<Vec<String>>>()?;
+        if alphabet != rules.alphabet {
+            return Err(invalid_data(&format!(
+                "the dictionary was compiled for the alphabet {}, not {}",
+                alphabet.join(" "),
+                rules.alphabet.join(" ")
+            )));
+        }
+        let mut gaddag = Gaddag::with_alphabet(&source, alphabet);
+        gaddag.words_nb = reader.u32()?;
+        let nodes_nb = reader.u32()? as usize;
+        let edges_nb = reader.u32()? as usize;
+        gaddag.nodes = Vec::with_capacity(nodes_nb);
+        for _ in 0..nodes_nb {
+            let first_edge = reader.u32()?;
+            let edges_nb = reader.u8()?;
+            let is_word = reader.u8()? != 0;
+            gaddag.nodes.push(Node {
+                first_edge,
+                edges_nb,
+                is_word,
+            });
+        }
+        gaddag.edges = Vec::with_capacity(edges_nb);
+        for _ in 0..edges_nb {
+            let child = reader.u32()?;
+            let letter = reader.u8()?;
+            gaddag.edges.push(Edge { letter, child });
+        }
+        // Vérifie que les index restent dans les bornes pour ne jamais paniquer à l'usage
+        let letters_nb = gaddag.alphabet.len() + 1;
+        if gaddag.nodes.is_empty()
+            || gaddag
+                .nodes
+                .iter()
+                .any(|n| n.first_edge as usize + n.edges_nb as usize > edges_nb)
+            || gaddag
+                .edges
+                .iter()
+                .any(|e| e.child as usize >= nodes_nb || e.letter as usize >= letters_nb)
+        {
+            return Err(invalid_data("corrupted dictionary file"));
+        }
+        Ok(gaddag)
     }
 
-    pub fn contains_word(word: &str, gaddag: &GaddagNode) -> bool {
-        // Vérifie si word est un mot valide du gaddag
-        if let Some(final_node) = Gaddag::follow_path(gaddag, word) {
-            final_node.borrow().is_word
+    pub fn open(filename: &str, rules: &RuleSet) -> io::Result<Self> {
+        // Charge un dictionnaire précompilé, ou à défaut une liste de mots
+        let mut header = [0; MAGIC.len()];
+        let is_compiled = {
+            let mut file = File::open(filename)?;
+            io::Read::read_exact(&mut file, &mut header).is_ok() && &header == MAGIC
+        };
+        if is_compiled {
+            Gaddag::load(filename, rules)
         } else {
-            false
+            Gaddag::read_words_from_file(filename, rules)
         }
     }
 
-    pub fn read_words_from_file(filename: &str) -> Rc<RefCell<Gaddag>> {
-        // Crée un nouveau gaddag qui contient tous les mots présents dans filename
-        let path = Path::new(filename);
-        let file = match File::open(&path) {
-            Ok(f) => f,
-            Err(_) => {
-                println!("Error opening file: {}", filename);
-                return Gaddag::new();
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn alphabet(&self) -> &[String] {
+        // Lettres des règles pour lesquelles le dictionnaire a été construit
+        &self.alphabet
+    }
+
+    pub fn words_nb(&self) -> usize {
+        self.words_nb as usize
+    }
+
+    pub fn rejected(&self) -> &[RejectedLine] {
+        &self.rejected
+    }
+
+    pub fn nodes_nb(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn edges_nb(&self) -> usize {
+        self.edges.len()
+    }
+
+    fn letter_index(tile: Tile) -> Option<u8> {
+        // Lettre d'une arête pour un jeton, le joker n'en ayant pas
+        match tile {
+            SEPARATOR => Some(0),
+            _ => tile.checked_add(1),
+        }
+    }
+
+    fn edges(&self, node: NodeId) -> &[Edge] {
+        let node = &self.nodes[node as usize];
+        let first = node.first_edge as usize;
+        &self.edges[first..first + node.edges_nb as usize]
+    }
+
+    pub fn root(&self) -> NodeId {
+        0
+    }
+
+    pub fn is_word(&self, node: NodeId) -> bool {
+        self.nodes[node as usize].is_word
+    }
+
+    pub fn child(&self, node: NodeId, tile: Tile) -> Option<NodeId> {
+        // Retourne le fils de node par le jeton tile
+        let letter = Gaddag::letter_index(tile)?;
+        self.edges(node)
+            .iter()
+            .find(|edge| edge.letter == letter)
+            .map(|edge| edge.child)
+    }
+
+    pub fn children(&self, node: NodeId) -> impl Iterator<Item = (Tile, NodeId)> + '_ {
+        // Parcourt les fils de node dans l'ordre de l'alphabet, le séparateur en premier
+        self.edges(node).iter().map(|edge| {
+            let tile = match edge.letter {
+                0 => SEPARATOR,
+                letter => letter - 1,
+            };
+            (tile, edge.child)
+        })
+    }
+
+    pub fn follow_path(&self, node: NodeId, path: &[Tile]) -> Option<NodeId> {
+        // Retourne le noeud en partant de node et en suivant path
+        let mut current_node = node;
+        for &tile in path {
+            current_node = self.child(current_node, tile)?;
+        }
+        Some(current_node)
+    }
+
+    pub fn contains_word(&self, word: &[Tile]) -> bool {
+        // Vérifie si word, écrit avec un séparateur comme dans le gaddag, y est un mot valide
+        match self.follow_path(self.root(), word) {
+            Some(final_node) => self.is_word(final_node),
+            None => false,
+        }
+    }
+
+    pub fn is_valid_word(&self, word: &[Tile]) -> bool {
+        // Vérifie si word est dans le dictionnaire, en le cherchant depuis sa première lettre
+        match word.split_first() {
+            Some((&first, rest)) => self
+                .child(self.root(), first)
+                .and_then(|node| self.child(node, SEPARATOR))
+                .and_then(|node| self.follow_path(node, rest))
+                .is_some_and(|node| self.is_word(node)),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORDS: [&str; 6] = ["KALIS", "HERON", "AS", "SA", "ZOO", "OUI"];
+
+    fn temp_file(name: &str) -> String {
+        // Fichier propre à chaque test, les tests tournant en parallèle
+        std::env::temp_dir()
+            .join(format!("scrabble-{}-{}.gdg", std::process::id(), name))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn all_words(gaddag: &Gaddag, rules: &RuleSet) -> Vec<String> {
+        // Chaque mot est rangé une fois sous sa première lettre suivie du séparateur
+        fn collect(
+            gaddag: &Gaddag,
+            node: NodeId,
+            word: &mut Vec<Tile>,
+            words: &mut Vec<Vec<Tile>>,
+        ) {
+            if gaddag.is_word(node) {
+                words.push(word.clone());
             }
-        };
-        let reader = io::BufReader::new(file);
-        let gaddag = Gaddag::new();
-        // Chaque ligne correspond à un mot
-        for line in reader.lines() {
-            match line {
-                Ok(word) => {
-                    Gaddag::generate_permutations(&word, Rc::clone(&gaddag));
-                }
-                Err(_) => {
-                    println!("Error reading a line from file: {}", filename);
-                    return Gaddag::new();
-                }
+            for (tile, child) in gaddag.children(node) {
+                word.push(tile);
+                collect(gaddag, child, word, words);
+                word.pop();
             }
         }
-        gaddag
+        let mut words = Vec::new();
+        for (tile, node) in gaddag.children(gaddag.root()) {
+            if let Some(node) = gaddag.child(node, SEPARATOR) {
+                collect(gaddag, node, &mut vec![tile], &mut words);
+            }
+        }
+        let mut words: Vec<String> = words.iter().map(|word| rules.tiles_text(word)).collect();
+        words.sort();
+        words
+    }
+
+    fn saved_bytes(name: &str) -> (String, Vec<u8>) {
+        let filename = temp_file(name);
+        Gaddag::from_words(&WORDS, "test.txt", &RuleSet::french())
+            .save(&filename)
+            .unwrap();
+        let data = fs::read(&filename).unwrap();
+        (filename, data)
+    }
+
+    #[test]
+    fn save_and_load_keep_the_same_words() {
+        let rules = RuleSet::french();
+        let gaddag = Gaddag::from_words(&WORDS, "test.txt", &rules);
+        let filename = temp_file("round-trip");
+        gaddag.save(&filename).unwrap();
+        let loaded = Gaddag::load(&filename, &rules);
+        fs::remove_file(&filename).unwrap();
+        let loaded = loaded.unwrap();
+        let mut expected: Vec<String> = WORDS.iter().map(|word| word.to_string()).collect();
+        expected.sort();
+        assert_eq!(all_words(&gaddag, &rules), expected);
+        assert_eq!(all_words(&loaded, &rules), expected);
+        assert_eq!(loaded.source(), "test.txt");
+        assert_eq!(loaded.alphabet(), gaddag.alphabet());
+        assert_eq!(loaded.words_nb(), WORDS.len());
+        assert_eq!(loaded.nodes_nb(), gaddag.nodes_nb());
+        assert_eq!(loaded.edges_nb(), gaddag.edges_nb());
+    }
+
+    #[test]
+    fn load_rejects_a_wrong_magic_or_version() {
+        let (filename, data) = saved_bytes("header");
+        let mut wrong_magic = data.clone();
+        wrong_magic[0] = b'X';
+        fs::write(&filename, &wrong_magic).unwrap();
+        let magic = Gaddag::load(&filename, &RuleSet::french());
+        let mut wrong_version = data;
+        wrong_version[MAGIC.len()..MAGIC.len() + 2]
+            .copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        fs::write(&filename, &wrong_version).unwrap();
+        let version = Gaddag::load(&filename, &RuleSet::french());
+        fs::remove_file(&filename).unwrap();
+        assert_eq!(magic.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(version.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn load_rejects_a_truncated_file() {
+        let (filename, data) = saved_bytes("truncated");
+        let mut results = Vec::new();
+        for length in [MAGIC.len() + 1, data.len() / 2, data.len() - 1] {
+            fs::write(&filename, &data[..length]).unwrap();
+            results.push(Gaddag::load(&filename, &RuleSet::french()));
+        }
+        fs::remove_file(&filename).unwrap();
+        for result in results {
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn duplicate_lines_count_as_one_word() {
+        let gaddag =
+            Gaddag::from_words(&["AS", "as", " AS ", "SA", ""], "test", &RuleSet::french());
+        assert_eq!(gaddag.words_nb(), 2);
+        assert!(gaddag.rejected().is_empty());
+    }
+
+    #[test]
+    fn words_are_normalised_before_being_split() {
+        let rules = RuleSet::french();
+        let gaddag = Gaddag::from_words(&["été", "Cœur", "garçon"], "test", &rules);
+        assert_eq!(all_words(&gaddag, &rules), ["COEUR", "ETE", "GARCON"]);
+    }
+
+    #[test]
+    fn spanish_keeps_the_n_with_tilde() {
+        let spanish = RuleSet::preset("spanish").unwrap();
+        let tile = |name: &str| spanish.tile(name).unwrap();
+        assert_eq!(
+            spanish.normalize_word("niño"),
+            Ok(vec![tile("N"), tile("I"), tile("Ñ"), tile("O")])
+        );
+        assert_eq!(spanish.normalize("canción"), "CANCION");
+    }
+
+    #[test]
+    fn multi_character_letters_take_the_longest_match() {
+        let spanish = RuleSet::preset("spanish").unwrap();
+        let tile = |name: &str| spanish.tile(name).unwrap();
+        assert_eq!(
+            spanish.normalize_word("chulla"),
+            Ok(vec![tile("CH"), tile("U"), tile("LL"), tile("A")])
+        );
+        let catalan = RuleSet::preset("catalan").unwrap();
+        let tiles = catalan.normalize_word("col·lecció").unwrap();
+        assert_eq!(catalan.tiles_text(&tiles), "COL·LECCIO");
+        assert_eq!(tiles.len(), 8);
+        assert_eq!(tiles[2], catalan.tile("L·L").unwrap());
+        let gaddag = Gaddag::from_words(&["col·lecció"], "test", &catalan);
+        assert_eq!(all_words(&gaddag, &catalan), ["COL·LECCIO"]);
+    }
+
+    #[test]
+    fn rejected_lines_keep_their_number() {
+        let gaddag = Gaddag::from_words(&["AS", "K2", "", "SA-MA"], "test", &RuleSet::french());
+        assert_eq!(gaddag.words_nb(), 1);
+        assert_eq!(
+            gaddag.rejected(),
+            [
+                RejectedLine {
+                    line: 2,
+                    text: "K2".to_string(),
+                    c: '2',
+                },
+                RejectedLine {
+                    line: 4,
+                    text: "SA-MA".to_string(),
+                    c: '-',
+                },
+            ]
+        );
+        assert_eq!(
+            gaddag.rejected()[1].to_string(),
+            "line 4: 'SA-MA' contains '-', which is not in the alphabet"
+        );
     }
 }
@@ -0,0 +1,435 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::bag::Bag;
+use crate::gaddag::Gaddag;
+use crate::grid::{Grid, MoveError};
+use crate::leave::LeaveTable;
+use crate::moves::Move;
+use crate::rack::{add_tiles, missing_tiles, rack_tiles, remove_tiles};
+use crate::rules::{RuleSet, Tile, BLANK};
+use crate::scoring::score_move;
+use crate::solver::generate_solutions_parallel;
+
+// Nombre de tours consécutifs sans points, passes et échanges compris, qui termine la partie
+pub const MAX_ZERO_TURNS: usize = 6;
+
+// Action d'un joueur à son tour
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Play(Move),
+    // Jetons rendus au sac contre autant de nouveaux
+    Exchange(Vec<Tile>),
+    Pass,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameError {
+    // Une partie a au moins un joueur
+    NoPlayers,
+    Over,
+    IllegalMove(MoveError),
+    // Jetons du coup ou de l'échange absents du chevalet
+    NotOnRack(Vec<Tile>),
+    // Le sac ne contient pas assez de jetons pour échanger
+    ExchangeNotAllowed { bag: usize, needed: usize },
+    EmptyExchange,
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameError::NoPlayers => write!(f, "a game needs at least one player"),
+            GameError::Over => write!(f, "the game is over"),
+            GameError::IllegalMove(error) => write!(f, "illegal move: {}", error),
+            GameError::NotOnRack(tiles) => {
+                write!(f, "{} tiles are not on the rack", tiles.len())
+            }
+            GameError::ExchangeNotAllowed { bag, needed } => write!(
+                f,
+                "exchanges need at least {} tiles in the bag, not {}",
+                needed, bag
+            ),
+            GameError::EmptyExchange => write!(f, "an exchange needs at least one tile"),
+        }
+    }
+}
+
+impl std::error::Error for GameError {}
+
+impl From<MoveError> for GameError {
+    fn from(error: MoveError) -> Self {
+        GameError::IllegalMove(error)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Player {
+    pub rack: HashMap<Tile, usize>,
+    pub score: i64,
+}
+
+// Tour joué, avec le chevalet du joueur avant son action
+#[derive(Debug, Clone)]
+pub struct GameTurn {
+    pub player: usize,
+    pub rack: HashMap<Tile, usize>,
+    pub action: Action,
+    pub score: usize,
+}
+
+// Fin de la partie et points retirés ou ajoutés à chaque joueur pour les jetons restants
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameEnd {
+    // Le joueur a posé tous ses jetons, le sac étant vide
+    Out {
+        player: usize,
+        adjustments: Vec<i64>,
+    },
+    // MAX_ZERO_TURNS tours de suite sans points
+    ZeroTurns {
+        adjustments: Vec<i64>,
+    },
+}
+
+impl GameEnd {
+    pub fn adjustments(&self) -> &[i64] {
+        match self {
+            GameEnd::Out { adjustments, .. } | GameEnd::ZeroTurns { adjustments } => adjustments,
+        }
+    }
+}
+
+// Partie classique : chaque joueur a son chevalet et joue à son tour
+pub struct Game {
+    pub grid: Grid,
+    pub bag: Bag,
+    pub players: Vec<Player>,
+    // Joueur dont c'est le tour
+    pub current: usize,
+    // Tours consécutifs sans points
+    pub zero_turns: usize,
+    pub turns: Vec<GameTurn>,
+    pub end: Option<GameEnd>,
+}
+
+fn best_exchange(rack: &HashMap<Tile, usize>, leaves: &LeaveTable) -> Option<(Vec<Tile>, f64)> {
+    // Échange qui garde le meilleur reliquat : chaque partie stricte du chevalet est essayée
+    // comme reliquat, le reste retournant au sac ; à valeur égale, on échange le plus de jetons
+    let tiles = rack_tiles(rack);
+    let mut best: Option<(Vec<Tile>, f64)> = None;
+    for mask in 0..(1u32 << tiles.len()).saturating_sub(1) {
+        let mut leave = HashMap::new();
+        let mut exchanged = Vec::new();
+        for (k, &tile) in tiles.iter().enumerate() {
+            if mask & (1 << k) != 0 {
+                add_tiles(&mut leave, &[tile]);
+            } else {
+                exchanged.push(tile);
+            }
+        }
+        let value = leaves.value(&leave);
+        if best.as_ref().is_none_or(|(best_tiles, best_value)| {
+            value > *best_value || (value == *best_value && exchanged.len() > best_tiles.len())
+        }) {
+            best = Some((exchanged, value));
+        }
+    }
+    best
+}
+
+impl Game {
+    pub fn new(
+        rules: Arc<RuleSet>,
+        players_nb: usize,
+        seed: Option<u64>,
+    ) -> Result<Self, GameError> {
+        // Crée la grille et le sac, puis tire le chevalet de chaque joueur
+        if players_nb == 0 {
+            return Err(GameError::NoPlayers);
+        }
+        let mut grid = Grid::with_rules(rules.clone());
+        grid.generate_grid();
+        let mut bag = Bag::with_rules(rules.clone(), seed);
+        let players = (0..players_nb)
+            .map(|_| {
+                let mut rack = HashMap::new();
+                bag.fill(&mut rack, rules.rack_size);
+                Player { rack, score: 0 }
+            })
+            .collect();
+        Ok(Game {
+            grid,
+            bag,
+            players,
+            current: 0,
+            zero_turns: 0,
+            turns: Vec::new(),
+            end: None,
+        })
+    }
+
+    pub fn rules(&self) -> &Arc<RuleSet> {
+        self.grid.rules()
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.end.is_some()
+    }
+
+    pub fn can_exchange(&self) -> bool {
+        // L'échange n'est permis que s'il reste au moins un chevalet complet dans le sac
+        self.bag.len() >= self.rules().rack_size
+    }
+
+    pub fn play(&mut self, action: Action, gaddag: &Gaddag) -> Result<(), GameError> {
+        // Joue l'action du joueur courant, pioche pour lui, puis passe au joueur suivant
+        if self.is_over() {
+            return Err(GameError::Over);
+        }
+        let rack_size = self.rules().rack_size;
+        let player = self.current;
+        let rack = self.players[player].rack.clone();
+        let mut score = 0;
+        match &action {
+            Action::Play(mv) => {
+                let breakdown = score_move(&self.grid, mv, gaddag)?;
+                let tiles: Vec<Tile> = self
+                    .grid
+                    .placed_tiles(mv)?
+                    .iter()
+                    .map(|tile| if tile.blank { BLANK } else { tile.letter })
+                    .collect();
+                let missing = missing_tiles(&rack, &tiles);
+                if !missing.is_empty() {
+                    return Err(GameError::NotOnRack(missing));
+                }
+                self.grid.play(mv, gaddag)?;
+                let rack = &mut self.players[player].rack;
+                remove_tiles(rack, &tiles);
+                self.bag.fill(rack, rack_size);
+                score = breakdown.total();
+            }
+            Action::Exchange(tiles) => {
+                if tiles.is_empty() {
+                    return Err(GameError::EmptyExchange);
+                }
+                if !self.can_exchange() {
+                    return Err(GameError::ExchangeNotAllowed {
+                        bag: self.bag.len(),
+                        needed: rack_size,
+                    });
+                }
+                self.bag
+                    .exchange(&mut self.players[player].rack, tiles)
+                    .map_err(GameError::NotOnRack)?;
+            }
+            Action::Pass => {}
+        }
+        self.players[player].score += score as i64;
+        self.zero_turns = if score == 0 { self.zero_turns + 1 } else { 0 };
+        self.turns.push(GameTurn {
+            player,
+            rack,
+            action,
+            score,
+        });
+        self.current = (player + 1) % self.players.len();
+        self.check_end(player);
+        Ok(())
+    }
+
+    fn check_end(&mut self, player: usize) {
+        // Termine la partie si le joueur a fini ou après trop de tours sans points
+        let values: Vec<i64> = self
+            .players
+            .iter()
+            .map(|p| self.rules().rack_value(&p.rack) as i64)
+            .collect();
+        let end = if self.players[player].rack.is_empty() && self.bag.is_empty() {
+            // Celui qui finit gagne la valeur des jetons des autres, qui la perdent
+            let mut adjustments: Vec<i64> = values.iter().map(|value| -value).collect();
+            adjustments[player] = values.iter().sum();
+            GameEnd::Out {
+                player,
+                adjustments,
+            }
+        } else if self.zero_turns >= MAX_ZERO_TURNS {
+            GameEnd::ZeroTurns {
+                adjustments: values.iter().map(|value| -value).collect(),
+            }
+        } else {
+            return;
+        };
+        for (player, adjustment) in self.players.iter_mut().zip(end.adjustments()) {
+            player.score += adjustment;
+        }
+        self.end = Some(end);
+    }
+
+    pub fn winners(&self) -> Vec<usize> {
+        // Joueurs ayant le meilleur score, plusieurs en cas d'égalité
+        let best = self.players.iter().map(|p| p.score).max().unwrap_or(0);
+        (0..self.players.len())
+            .filter(|&k| self.players[k].score == best)
+            .collect()
+    }
+
+    pub fn best_action(&self, gaddag: &Gaddag, leaves: &LeaveTable) -> Action {
+        // Joueur automatique : le meilleur coup selon son score et son reliquat, ou l'échange
+        // qui garde le meilleur reliquat s'il est permis et vaut plus, sinon une passe
+        let rack = &self.players[self.current].rack;
+        let mut moves = generate_solutions_parallel(&self.grid, rack, gaddag);
+        leaves.rank(&mut moves);
+        let exchange = if self.can_exchange() {
+            best_exchange(rack, leaves)
+        } else {
+            None
+        };
+        match (moves.into_iter().next(), exchange) {
+            (Some(mv), Some((tiles, value))) if value > leaves.equity(&mv) => {
+                Action::Exchange(tiles)
+            }
+            (Some(mv), _) => Action::Play(mv),
+            (None, Some((tiles, _))) => Action::Exchange(tiles),
+            (None, None) => Action::Pass,
+        }
+    }
+}
+
+pub fn play_classic_game(
+    gaddag: &Gaddag,
+    rules: Arc<RuleSet>,
+    players_nb: usize,
+    leaves: &LeaveTable,
+    seed: Option<u64>,
+) -> Result<Game, GameError> {
+    // Fait jouer une partie classique entre joueurs automatiques jusqu'à sa fin
+    let mut game = Game::new(rules, players_nb, seed)?;
+    while !game.is_over() {
+        let action = game.best_action(gaddag, leaves);
+        game.play(action, gaddag)
+            .expect("the automatic player only chooses legal actions");
+    }
+    Ok(game)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::Direction;
+
+    fn small_rules() -> Arc<RuleSet> {
+        // Règles françaises avec un chevalet de deux jetons
+        let mut rules = (*RuleSet::french()).clone();
+        rules.rack_size = 2;
+        Arc::new(rules)
+    }
+
+    fn game(racks: [&str; 2], bag: &str) -> (Game, Gaddag) {
+        // Partie à deux joueurs avec SAMA en H8, les chevalets et le sac donnés
+        let rules = small_rules();
+        let gaddag = Gaddag::from_words(&["SAMA", "SAMAS", "AS", "ZA", "ZAS"], "test", &rules);
+        let mut game = Game::new(rules.clone(), 2, Some(1)).unwrap();
+        game.grid
+            .play(&Move::new(7, 7, Direction::Horizontal, "SAMA"), &gaddag)
+            .unwrap();
+        for (player, rack) in game.players.iter_mut().zip(racks) {
+            player.rack = rules.parse_rack(rack).unwrap();
+        }
+        game.bag = Bag::from_tiles(rules.clone(), rules.parse_tiles(bag).unwrap(), Some(1));
+        (game, gaddag)
+    }
+
+    #[test]
+    fn a_game_needs_players() {
+        assert!(matches!(
+            Game::new(small_rules(), 0, None),
+            Err(GameError::NoPlayers)
+        ));
+        assert_eq!(Game::new(small_rules(), 2, None).unwrap().players.len(), 2);
+    }
+
+    #[test]
+    fn going_out_takes_the_other_racks() {
+        let (mut game, gaddag) = game(["Z", "KE"], "");
+        let mv = Move::new(6, 8, Direction::Vertical, "ZA");
+        game.play(Action::Play(mv), &gaddag).unwrap();
+        let score = game.turns[0].score as i64;
+        assert_eq!(
+            game.end,
+            Some(GameEnd::Out {
+                player: 0,
+                adjustments: vec![11, -11],
+            })
+        );
+        assert_eq!(game.players[0].score, score + 11);
+        assert_eq!(game.players[1].score, -11);
+        assert_eq!(game.winners(), vec![0]);
+        assert_eq!(game.play(Action::Pass, &gaddag), Err(GameError::Over));
+    }
+
+    #[test]
+    fn six_scoreless_turns_end_the_game() {
+        let (mut game, gaddag) = game(["ZE", "KE"], "AAAA");
+        for turn in 0..MAX_ZERO_TURNS {
+            assert!(!game.is_over());
+            let action = if turn == 0 {
+                Action::Exchange(vec![game.rules().tile("Z").unwrap()])
+            } else {
+                Action::Pass
+            };
+            game.play(action, &gaddag).unwrap();
+        }
+        let adjustments = match &game.end {
+            Some(GameEnd::ZeroTurns { adjustments }) => adjustments.clone(),
+            end => panic!("unexpected end {:?}", end),
+        };
+        assert_eq!(adjustments[1], -11);
+        assert_eq!(
+            adjustments[0],
+            -(game.rules().rack_value(&game.players[0].rack) as i64)
+        );
+        assert_eq!(game.players[1].score, -11);
+    }
+
+    #[test]
+    fn exchanges_are_checked() {
+        let (mut game, gaddag) = game(["EE", "KE"], "A");
+        let e = game.rules().tile("E").unwrap();
+        let k = game.rules().tile("K").unwrap();
+        assert_eq!(
+            game.play(Action::Exchange(vec![]), &gaddag),
+            Err(GameError::EmptyExchange)
+        );
+        assert_eq!(
+            game.play(Action::Exchange(vec![e]), &gaddag),
+            Err(GameError::ExchangeNotAllowed { bag: 1, needed: 2 })
+        );
+        game.bag = Bag::from_tiles(game.rules().clone(), vec![k, k, k], Some(1));
+        assert_eq!(
+            game.play(Action::Exchange(vec![e, e, e]), &gaddag),
+            Err(GameError::NotOnRack(vec![e]))
+        );
+        assert_eq!(game.players[0].rack, HashMap::from([(e, 2)]));
+        assert_eq!(game.bag.len(), 3);
+        game.play(Action::Exchange(vec![e]), &gaddag).unwrap();
+        assert_eq!(game.players[0].rack, HashMap::from([(e, 1), (k, 1)]));
+        assert_eq!(game.bag.len(), 3);
+        assert_eq!(game.current, 1);
+        assert_eq!(game.zero_turns, 1);
+    }
+
+    #[test]
+    fn moves_must_use_the_rack() {
+        let (mut game, gaddag) = game(["EE", "KE"], "");
+        let mv = Move::new(6, 8, Direction::Vertical, "ZA");
+        assert_eq!(
+            game.play(Action::Play(mv), &gaddag),
+            Err(GameError::NotOnRack(vec![game.rules().tile("Z").unwrap()]))
+        );
+        assert!(game.turns.is_empty());
+        assert_eq!(game.current, 0);
+    }
+}
@@ -1,38 +1,47 @@
 use std::collections::HashMap;
-use std::time::Instant;
 
 use crate::bag::Bag;
-use crate::gaddag::GaddagNode;
-use crate::solver::generate_solutions;
-use crate::Grid;
+use crate::gaddag::Gaddag;
+use crate::grid::Grid;
+use crate::leave::LeaveTable;
+use crate::moves::Move;
+use crate::rules::Tile;
+use crate::solver::generate_solutions_parallel;
 
-pub fn generate_game(gaddag: &GaddagNode) {
+pub struct Turn {
+    pub rack: HashMap<Tile, usize>,
+    pub top: Move,
+}
+
+pub fn generate_game(gaddag: &Gaddag, bag: &mut Bag, leaves: &LeaveTable) -> Vec<Turn> {
+    // Génère une partie en jouant à chaque coup le meilleur mot possible, selon son score et la
+    // valeur des lettres gardées
     // Initialisation
-    let start = Instant::now();
-    let mut grid = Grid::new();
+    let mut grid = Grid::with_rules(bag.rules().clone());
     Grid::generate_grid(&mut grid);
-    let mut bag = Bag::new();
+    let rack_size = bag.rules().rack_size;
     let mut rack = HashMap::new();
-    let mut moves_nb = 0;
+    let mut turns = Vec::new();
     let mut min_vc = 2;
     // Génération
-    while bag.valid_draw(&mut rack, 7, min_vc) {
-        println!("Move: {}, Rack: {:?}", moves_nb, rack);
-        let mut valid_words = generate_solutions(&grid, &rack, &gaddag);
-        valid_words.sort_by(|a, b| b.score.cmp(&a.score));
-        let top = &valid_words[0];
-        println!(
-            "Position: {}, Word: {}, Score: {}, Remaining Rack: {:?}",
-            top.position, top.word, top.score, top.rack
-        );
-        let ((i, j), direction) = Grid::ref_to_pos(&top.position);
-        grid.play(&top.word, i, j, direction, &gaddag);
-        rack = top.rack.clone();
-        moves_nb += 1;
-        if moves_nb > 15 {
+    while bag.valid_draw(&mut rack, rack_size, min_vc) {
+        let mut valid_words = generate_solutions_parallel(&grid, &rack, gaddag);
+        leaves.rank(&mut valid_words);
+        // On s'arrête si aucun mot n'est jouable
+        if valid_words.is_empty() {
+            break;
+        }
+        let top = valid_words.swap_remove(0);
+        grid.play(&top, gaddag)
+            .expect("the solver only generates legal moves");
+        let previous_rack = std::mem::replace(&mut rack, top.leave.clone());
+        turns.push(Turn {
+            rack: previous_rack,
+            top,
+        });
+        if turns.len() > 15 {
             min_vc = 1;
         }
     }
-    let duration = start.elapsed();
-    println!("Le temps d'exécution est de: {:?}", duration);
+    turns
 }
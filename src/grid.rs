@@ -1,42 +1,155 @@
 use std::collections::HashMap;
 use std::fmt;
+use std::sync::Arc;
 
-use crate::constants::{ALPHABET, BONUS_CELLS, GRID_SIZE, LETTERS_VALUE};
-use crate::gaddag::{Gaddag, GaddagNode};
+use crate::export::grid_to_text;
+use crate::gaddag::{Gaddag, SEPARATOR};
+use crate::moves::{Direction, Move, PlacedTile};
+pub use crate::rules::Square;
+use crate::rules::{Letter, RuleSet, Tile, BLANK};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CoordError {
+    Empty,
+    Malformed(String),
+    // Rangée ou colonne hors d'une grille de size cases de côté
+    RowOutOfRange { row: char, size: usize },
+    ColumnOutOfRange { col: String, size: usize },
+}
+
+impl fmt::Display for CoordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoordError::Empty => write!(f, "empty coordinates"),
+            CoordError::Malformed(reference) => write!(
+                f,
+                "malformed coordinates \"{}\": expected a row letter and a column number, \
+                 like H8 (horizontal) or 8H (vertical)",
+                reference
+            ),
+            CoordError::RowOutOfRange { row, size } => {
+                let last_row = (b'A' + *size as u8 - 1) as char;
+                write!(f, "row {} is outside the board (A to {})", row, last_row)
+            }
+            CoordError::ColumnOutOfRange { col, size } => {
+                write!(f, "column {} is outside the board (1 to {})", col, size)
+            }
+        }
+    }
+}
 
-pub enum Square {
-    Blank,
-    LCD,
-    LCT,
-    LCQ,
-    MCD,
-    MCT,
-    MCQ,
-    Letter(char),
+impl std::error::Error for CoordError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoveError {
+    TooShort,
+    InvalidLetter(char),
+    OutOfBounds,
+    Conflict {
+        row: usize,
+        col: usize,
+        on_board: String,
+        played: String,
+    },
+    NoTilePlaced,
+    NotWholeWord(String),
+    CenterNotCovered,
+    NotConnected,
+    InvalidWord(String),
+    InvalidCrossword {
+        row: usize,
+        col: usize,
+        word: String,
+    },
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let square = |row: usize, col: usize| format!("{}{}", (b'A' + row as u8) as char, col + 1);
+        match self {
+            MoveError::TooShort => write!(f, "a word must have at least two letters"),
+            MoveError::InvalidLetter(c) => {
+                write!(f, "'{}' does not start a letter of the alphabet", c)
+            }
+            MoveError::OutOfBounds => write!(f, "the word goes off the board"),
+            MoveError::Conflict {
+                row,
+                col,
+                on_board,
+                played,
+            } => write!(
+                f,
+                "square {} already holds {}, not {}",
+                square(*row, *col),
+                on_board,
+                played
+            ),
+            MoveError::NoTilePlaced => write!(f, "the move does not place any tile"),
+            MoveError::NotWholeWord(word) => {
+                write!(
+                    f,
+                    "the word touches other letters, the full word would be {}",
+                    word
+                )
+            }
+            MoveError::CenterNotCovered => write!(f, "the first move must cover the start square"),
+            MoveError::NotConnected => {
+                write!(f, "the word is not connected to the tiles on the board")
+            }
+            MoveError::InvalidWord(word) => write!(f, "{} is not in the dictionary", word),
+            MoveError::InvalidCrossword { row, col, word } => write!(
+                f,
+                "the tile on {} forms {}, which is not in the dictionary",
+                square(*row, *col),
+                word
+            ),
+        }
+    }
 }
 
+impl std::error::Error for MoveError {}
+
+#[derive(Clone)]
 pub struct Grid {
-    pub squares: [[Square; GRID_SIZE]; GRID_SIZE],
-    pub anchors: [[bool; GRID_SIZE]; GRID_SIZE],
-    pub crosswords: [[Option<HashMap<char, usize>>; GRID_SIZE]; GRID_SIZE],
+    pub squares: Vec<Vec<Square>>,
+    pub anchors: Vec<Vec<bool>>,
+    // Lettres jouables sur chaque ancre avec le score du mot croisé formé, BLANK pour un joker
+    pub crosswords: Vec<Vec<Option<HashMap<Tile, usize>>>>,
+    // Nombre de rangées et de colonnes, donné par la grille des règles
+    size: usize,
+    rules: Arc<RuleSet>,
 }
 
 impl Grid {
     pub fn new() -> Self {
+        // Grille vide aux règles francophones
+        Grid::with_rules(RuleSet::french())
+    }
+
+    pub fn with_rules(rules: Arc<RuleSet>) -> Self {
+        let size = rules.size();
         Grid {
-            squares: [[Square::Blank; GRID_SIZE]; GRID_SIZE],
-            anchors: [[false; GRID_SIZE]; GRID_SIZE],
-            crosswords: std::array::from_fn(|_| std::array::from_fn(|_| None)),
+            squares: vec![vec![Square::Blank; size]; size],
+            anchors: vec![vec![false; size]; size],
+            crosswords: vec![vec![None; size]; size],
+            size,
+            rules,
         }
     }
 
-    pub fn transpose_grid(&self, gaddag: &GaddagNode) -> Grid {
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn rules(&self) -> &Arc<RuleSet> {
+        &self.rules
+    }
+
+    pub fn transpose_grid(&self, gaddag: &Gaddag) -> Grid {
         // Renvoie une copie transposée de la grille
-        let mut transposed_grid = Self::new();
-        for i in 0..GRID_SIZE {
-            for j in 0..GRID_SIZE {
+        let mut transposed_grid = Grid::with_rules(self.rules.clone());
+        for i in 0..self.size {
+            for j in 0..self.size {
                 transposed_grid.squares[j][i] = self.squares[i][j];
             }
         }
@@ -45,84 +158,70 @@ impl Grid {
         transposed_grid
     }
 
-    pub fn pos_to_ref(position: (usize, usize), direction: bool) -> String {
-        // Transforme une position en coordonnées
+    pub fn in_bounds(&self, i: usize, j: usize) -> bool {
+        i < self.size && j < self.size
+    }
+
+    pub fn pos_to_ref(position: (usize, usize), direction: Direction) -> String {
+        // Transforme une position en coordonnées : la ligne est une lettre, la colonne un nombre,
+        // et la ligne vient en premier pour un mot horizontal
         let (row, col) = position;
-        if direction {
-            let letter = (b'A' + row as u8) as char;
-            let number = col + 1;
-            format!("{}{}", letter, number)
-        } else {
-            let letter = (b'A' + col as u8) as char;
-            let number = row + 1;
-            format!("{}{}", number, letter)
-        }
-    }
-
-    pub fn ref_to_pos(reference: &str) -> ((usize, usize), bool) {
-        // Transforme des coordonnées en position
-        let chars: Vec<char> = reference.chars().collect();
-        let len = chars.len();
-        match len {
-            2 => {
-                if chars[0].is_ascii_alphabetic() && chars[1].is_ascii_digit() {
-                    let row = (chars[0] as usize) - 'A' as usize;
-                    let col = (chars[1] as usize) - '1' as usize;
-                    return ((row, col), true);
-                } else if chars[0].is_ascii_digit() && chars[1].is_ascii_alphabetic() {
-                    let row = (chars[1] as usize) - 'A' as usize;
-                    let col = (chars[0] as usize) - '1' as usize;
-                    return ((row, col), false);
-                }
-            }
-            3 => {
-                if chars[0].is_ascii_alphabetic()
-                    && chars[1].is_ascii_digit()
-                    && chars[2].is_ascii_digit()
-                {
-                    let row = (chars[0] as usize) - 'A' as usize;
-                    let col = reference[1..].parse::<usize>().unwrap_or(0) - 1;
-                    return ((row, col), true);
-                } else if chars[2].is_ascii_alphabetic()
-                    && chars[0].is_ascii_digit()
-                    && chars[1].is_ascii_digit()
-                {
-                    let row = (chars[2] as usize) - 'A' as usize;
-                    let col = reference[..2].parse::<usize>().unwrap_or(0) - 1;
-                    return ((row, col), false);
-                }
-            }
-            _ => {}
+        let letter = (b'A' + row as u8) as char;
+        let number = col + 1;
+        match direction {
+            Direction::Horizontal => format!("{}{}", letter, number),
+            Direction::Vertical => format!("{}{}", number, letter),
         }
-        ((GRID_SIZE, GRID_SIZE), true)
     }
 
-    fn set_bonus(&mut self, bonus: Square, idx_list: &[(usize, usize)]) {
-        let n = GRID_SIZE - 1;
-        for (x, y) in idx_list {
-            self.squares[*x][*y] = bonus;
-            self.squares[*x][n - *y] = bonus;
-            self.squares[n - *x][*y] = bonus;
-            self.squares[n - *x][n - *y] = bonus;
+    pub fn ref_to_pos(
+        reference: &str,
+        size: usize,
+    ) -> Result<((usize, usize), Direction), CoordError> {
+        // Transforme des coordonnées en position sur une grille de size cases de côté :
+        // "H8" est horizontal, "8H" est vertical
+        let reference = reference.trim();
+        if reference.is_empty() {
+            return Err(CoordError::Empty);
         }
+        let upper = reference.to_ascii_uppercase();
+        let (letter, number, direction) = if upper.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            (&upper[..1], &upper[1..], Direction::Horizontal)
+        } else if upper.ends_with(|c: char| c.is_ascii_alphabetic()) {
+            let k = upper.len() - 1;
+            (&upper[k..], &upper[..k], Direction::Vertical)
+        } else {
+            return Err(CoordError::Malformed(reference.to_string()));
+        };
+        if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(CoordError::Malformed(reference.to_string()));
+        }
+        let letter = letter.chars().next().unwrap();
+        let row = (letter as u8 - b'A') as usize;
+        if row >= size {
+            return Err(CoordError::RowOutOfRange { row: letter, size });
+        }
+        let col = match number.parse::<usize>() {
+            Ok(n) if (1..=size).contains(&n) => n - 1,
+            _ => {
+                return Err(CoordError::ColumnOutOfRange {
+                    col: number.to_string(),
+                    size,
+                })
+            }
+        };
+        Ok(((row, col), direction))
     }
 
     pub fn generate_grid(&mut self) {
-        // Place les cases bonus sur la grille et l'ancre au centre
-        let bonuses = [
-            ("LCD", Square::LCD),
-            ("LCT", Square::LCT),
-            ("LCQ", Square::LCQ),
-            ("MCD", Square::MCD),
-            ("MCT", Square::MCT),
-            ("MCQ", Square::MCQ),
-        ];
-        for (str_bonus, bonus) in bonuses {
-            if let Some(idx_list) = BONUS_CELLS.get(str_bonus) {
-                self.set_bonus(bonus, idx_list);
+        // Place les cases bonus des règles sur la grille et l'ancre sur la case de départ
+        for (i, row) in self.rules.premiums().into_iter().enumerate() {
+            for (j, square) in row.into_iter().enumerate() {
+                self.squares[i][j] = square;
             }
         }
-        self.anchors[7][7] = true;
+        let (i, j) = self.rules.start;
+        self.anchors[i][j] = true;
     }
 
     pub fn get_square_multiplier(&self, x: usize, y: usize) -> (usize, usize) {
@@ -139,74 +238,250 @@ impl Grid {
         }
     }
 
-    pub fn play(&mut self, word: &str, i: usize, j: usize, direction: bool, gaddag: &GaddagNode) {
-        if direction {
-            // Mot horizontal
-            for (k, c) in word.chars().enumerate() {
-                self.squares[i][j + k] = Square::Letter(c);
+    pub fn cells(&self, mv: &Move) -> Result<Vec<(usize, usize, Letter)>, MoveError> {
+        // Découpe le mot d'un coup en lettres des règles, avec la case de chacune
+        let letters = self
+            .rules
+            .parse_letters(&mv.word)
+            .map_err(MoveError::InvalidLetter)?;
+        Ok(letters
+            .into_iter()
+            .enumerate()
+            .map(|(k, letter)| {
+                let (i, j) = mv.direction.offset(mv.row, mv.col, k);
+                (i, j, letter)
+            })
+            .collect())
+    }
+
+    pub fn placed_tiles(&self, mv: &Move) -> Result<Vec<PlacedTile>, MoveError> {
+        // Retourne les jetons du coup qui ne sont pas déjà sur la grille
+        Ok(self
+            .cells(mv)?
+            .into_iter()
+            .filter(|&(i, j, _)| self.is_empty(i, j))
+            .map(|(row, col, letter)| PlacedTile {
+                row,
+                col,
+                letter: letter.tile,
+                blank: letter.blank,
+            })
+            .collect())
+    }
+
+    pub fn letter(&self, i: usize, j: usize) -> Option<Letter> {
+        // Retourne le jeton posé sur la case (i, j)
+        if !self.in_bounds(i, j) {
+            return None;
+        }
+        match self.squares[i][j] {
+            Square::Letter(letter) => Some(letter),
+            _ => None,
+        }
+    }
+
+    pub fn word_start(&self, i: usize, j: usize, direction: Direction) -> (usize, usize) {
+        // Retourne la première case du mot qui passe par (i, j) dans direction
+        let (di, dj) = direction.offset(0, 0, 1);
+        let (mut si, mut sj) = (i, j);
+        while si >= di && sj >= dj && self.letter(si - di, sj - dj).is_some() {
+            si -= di;
+            sj -= dj;
+        }
+        (si, sj)
+    }
+
+    pub fn word_through(
+        &self,
+        i: usize,
+        j: usize,
+        letter: Letter,
+        direction: Direction,
+    ) -> Vec<Letter> {
+        // Retourne le mot formé dans direction en posant letter sur la case vide (i, j)
+        let (di, dj) = direction.offset(0, 0, 1);
+        let (mut x, mut y) = self.word_start(i, j, direction);
+        let mut word = Vec::new();
+        loop {
+            let current = if (x, y) == (i, j) {
+                Some(letter)
+            } else {
+                self.letter(x, y)
+            };
+            match current {
+                Some(current) => word.push(current),
+                None => break,
             }
-        } else {
-            // Mot vertical
-            for (k, c) in word.chars().enumerate() {
-                self.squares[i + k][j] = Square::Letter(c);
+            x += di;
+            y += dj;
+        }
+        word
+    }
+
+    fn extended_word(&self, mv: &Move, letters: &[Letter]) -> Vec<Letter> {
+        // Retourne les lettres de mv prolongées par celles de la grille qui les touchent
+        let (di, dj) = mv.direction.offset(0, 0, 1);
+        let mut word = Vec::new();
+        let (mut x, mut y) = (mv.row, mv.col);
+        while x >= di && y >= dj {
+            x -= di;
+            y -= dj;
+            match self.letter(x, y) {
+                Some(letter) => word.insert(0, letter),
+                None => break,
+            }
+        }
+        word.extend_from_slice(letters);
+        let (mut x, mut y) = mv.direction.offset(mv.row, mv.col, letters.len());
+        while let Some(letter) = self.letter(x, y) {
+            word.push(letter);
+            x += di;
+            y += dj;
+        }
+        word
+    }
+
+    fn tiles(letters: &[Letter]) -> Vec<Tile> {
+        letters.iter().map(|letter| letter.tile).collect()
+    }
+
+    pub fn validate_move(&self, mv: &Move, gaddag: &Gaddag) -> Result<(), MoveError> {
+        // Vérifie qu'un coup est légal et renvoie la raison sinon
+        let cells = self.cells(mv)?;
+        let len = cells.len();
+        if len < 2 {
+            return Err(MoveError::TooShort);
+        }
+        let (end_i, end_j) = mv.direction.offset(mv.row, mv.col, len - 1);
+        if !self.in_bounds(mv.row, mv.col) || !self.in_bounds(end_i, end_j) {
+            return Err(MoveError::OutOfBounds);
+        }
+        // Les lettres déjà posées doivent correspondre
+        for &(i, j, letter) in &cells {
+            if let Some(on_board) = self.letter(i, j) {
+                if on_board.tile != letter.tile {
+                    return Err(MoveError::Conflict {
+                        row: i,
+                        col: j,
+                        on_board: self.rules.letter_text(on_board),
+                        played: self.rules.letter_text(letter),
+                    });
+                }
             }
         }
-        self.update_anchors();
-        self.update_crosswords(&gaddag);
+        let placed = self.placed_tiles(mv)?;
+        if placed.is_empty() {
+            return Err(MoveError::NoTilePlaced);
+        }
+        // Le mot ne doit pas être prolongé par des lettres avant ou après lui
+        let letters: Vec<Letter> = cells.iter().map(|&(_, _, letter)| letter).collect();
+        let whole_word = Grid::tiles(&self.extended_word(mv, &letters));
+        if whole_word.len() != len {
+            return Err(MoveError::NotWholeWord(self.rules.tiles_text(&whole_word)));
+        }
+        // Premier coup sur la case de départ, sinon le mot doit toucher une lettre existante
+        let start = self.rules.start;
+        let board_is_empty = (0..self.size).all(|i| (0..self.size).all(|j| self.is_empty(i, j)));
+        if board_is_empty {
+            if !cells.iter().any(|&(i, j, _)| (i, j) == start) {
+                return Err(MoveError::CenterNotCovered);
+            }
+        } else if placed.len() == len && !placed.iter().any(|t| self.anchors[t.row][t.col]) {
+            return Err(MoveError::NotConnected);
+        }
+        // Le mot principal et tous les mots croisés doivent être dans le dictionnaire
+        if !gaddag.is_valid_word(&whole_word) {
+            return Err(MoveError::InvalidWord(self.rules.tiles_text(&whole_word)));
+        }
+        for tile in &placed {
+            let letter = Letter {
+                tile: tile.letter,
+                blank: tile.blank,
+            };
+            let crossword =
+                Grid::tiles(&self.word_through(tile.row, tile.col, letter, mv.direction.other()));
+            if crossword.len() > 1 && !gaddag.is_valid_word(&crossword) {
+                return Err(MoveError::InvalidCrossword {
+                    row: tile.row,
+                    col: tile.col,
+                    word: self.rules.tiles_text(&crossword),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn play(&mut self, mv: &Move, gaddag: &Gaddag) -> Result<(), MoveError> {
+        // Joue un coup sur la grille s'il est légal
+        self.validate_move(mv, gaddag)?;
+        self.place(mv, gaddag);
+        Ok(())
+    }
+
+    pub(crate) fn place(&mut self, mv: &Move, gaddag: &Gaddag) {
+        // Pose les lettres d'un coup déjà validé
+        let mut changed = Vec::new();
+        for (i, j, letter) in self.cells(mv).unwrap_or_default() {
+            if self.is_empty(i, j) {
+                self.squares[i][j] = Square::Letter(letter);
+                changed.push((i, j));
+            }
+        }
+        self.update_around(&changed, gaddag);
     }
 
     pub fn is_empty(&self, i: usize, j: usize) -> bool {
-        if i >= GRID_SIZE || j >= GRID_SIZE {
+        if !self.in_bounds(i, j) {
             return true;
         }
-        match self.squares[i][j] {
-            Square::Letter(_) => false,
-            _ => true,
-        }
+        !matches!(self.squares[i][j], Square::Letter(_))
+    }
+
+    fn update_anchor(&mut self, i: usize, j: usize) {
+        // Une case vide est une ancre si elle touche une lettre
+        self.anchors[i][j] = self.is_empty(i, j)
+            && !(self.is_empty(i.wrapping_sub(1), j)
+                && self.is_empty(i + 1, j)
+                && self.is_empty(i, j.wrapping_sub(1))
+                && self.is_empty(i, j + 1));
     }
 
     pub fn update_anchors(&mut self) {
-        for i in 0..GRID_SIZE {
-            for j in 0..GRID_SIZE {
-                if self.is_empty(i, j) {
-                    let b1 = self.is_empty(i.wrapping_sub(1), j);
-                    let b2 = self.is_empty(i + 1, j);
-                    let b3 = self.is_empty(i, j.wrapping_sub(1));
-                    let b4 = self.is_empty(i, j + 1);
-                    self.anchors[i][j] = !(b1 && b2 && b3 && b4);
-                } else {
-                    self.anchors[i][j] = false;
-                }
+        for i in 0..self.size {
+            for j in 0..self.size {
+                self.update_anchor(i, j);
             }
         }
         if self.anchors.iter().all(|row| row.iter().all(|&a| !a)) {
-            self.anchors[7][7] = true; // Activation de l'ancre centrale si la grille est vide
+            // Activation de l'ancre de départ si la grille est vide
+            let (i, j) = self.rules.start;
+            self.anchors[i][j] = true;
         }
     }
 
-    fn adj(&self, x: usize, y: usize) -> (String, String, usize) {
+    fn adj(&self, x: usize, y: usize) -> (Vec<Tile>, Vec<Tile>, usize) {
         // Récupère les mots au dessus et en dessous de la case actuelle
-        let mut up_letters = String::new();
-        let mut down_letters = String::new();
+        let mut up_letters = Vec::new();
+        let mut down_letters = Vec::new();
         let mut score = 0;
         // Parcours vers le haut
         let mut i = x;
         while i > 0 {
             i -= 1;
-            if let Square::Letter(c) = self.squares[i][y] {
-                score += *LETTERS_VALUE.get(&c).unwrap_or(&0);
-                up_letters.push(c.to_ascii_uppercase());
+            if let Square::Letter(letter) = self.squares[i][y] {
+                score += self.rules.letter_value(letter);
+                up_letters.push(letter.tile);
             } else {
                 break;
             }
         }
         // Parcours vers le bas
         i = x;
-        while i < 14 {
+        while i + 1 < self.size {
             i += 1;
-            if let Square::Letter(c) = self.squares[i][y] {
-                score += *LETTERS_VALUE.get(&c).unwrap_or(&0);
-                down_letters.push(c.to_ascii_uppercase());
+            if let Square::Letter(letter) = self.squares[i][y] {
+                score += self.rules.letter_value(letter);
+                down_letters.push(letter.tile);
             } else {
                 break;
             }
@@ -214,87 +489,336 @@ impl Grid {
         (up_letters, down_letters, score)
     }
 
-    pub fn update_crosswords(&mut self, gaddag: &GaddagNode) {
-        for x in 0..GRID_SIZE {
-            for y in 0..GRID_SIZE {
-                self.crosswords[x][y] = None;
-                if self.anchors[x][y] {
-                    let (up_letters, down_letters, score) = self.adj(x, y);
-                    // On regarde s'il y a des lettres en haut ou en bas de la case
-                    if up_letters != "" || down_letters != "" {
-                        let (flat, mult) = self.get_square_multiplier(x, y);
-                        for &c in &ALPHABET {
-                            let word = format!("{}{}!{}", c, up_letters, down_letters);
-                            if Gaddag::contains_word(&word, gaddag) {
-                                // Calcul du score du crossword
-                                let letter_score = *LETTERS_VALUE.get(&c).unwrap_or(&0);
-                                let cw_score = (letter_score * flat + score) * mult;
-                                // Insertion de la lettre et du score dans la table
-                                let entry = self.crosswords[x][y].get_or_insert_with(HashMap::new);
-                                entry.insert(c, cw_score);
-                            }
-                        }
-                        if let Some(entry) = &mut self.crosswords[x][y] {
-                            // Insertion du joker s'il existe au moins un crossword possible
-                            let jok_cw_score = score * mult;
-                            entry.insert('?', jok_cw_score);
-                        } else {
-                            // Sinon on initialise avec une HashMap vide
-                            self.crosswords[x][y] = Some(HashMap::new());
-                        }
+    fn update_crossword(&mut self, x: usize, y: usize, gaddag: &Gaddag) {
+        // Calcule les lettres jouables sur la case (x, y) et le score du mot croisé formé
+        self.crosswords[x][y] = None;
+        if self.anchors[x][y] {
+            let (up_letters, down_letters, score) = self.adj(x, y);
+            // On regarde s'il y a des lettres en haut ou en bas de la case
+            if !up_letters.is_empty() || !down_letters.is_empty() {
+                let (flat, mult) = self.get_square_multiplier(x, y);
+                // Mot du gaddag : la lettre posée, le haut renversé, le séparateur, le bas
+                let mut word = vec![BLANK];
+                word.extend(&up_letters);
+                word.push(SEPARATOR);
+                word.extend(&down_letters);
+                let mut playable = Vec::new();
+                for tile in self.rules.letters() {
+                    word[0] = tile;
+                    if gaddag.contains_word(&word) {
+                        // Calcul du score du crossword
+                        playable.push((tile, (self.rules.value(tile) * flat + score) * mult));
                     }
                 }
+                for (tile, cw_score) in playable {
+                    // Insertion de la lettre et du score dans la table
+                    let entry = self.crosswords[x][y].get_or_insert_with(HashMap::new);
+                    entry.insert(tile, cw_score);
+                }
+                if let Some(entry) = &mut self.crosswords[x][y] {
+                    // Insertion du joker s'il existe au moins un crossword possible
+                    let jok_cw_score = score * mult;
+                    entry.insert(BLANK, jok_cw_score);
+                } else {
+                    // Sinon on initialise avec une HashMap vide
+                    self.crosswords[x][y] = Some(HashMap::new());
+                }
+            }
+        }
+    }
+
+    pub fn update_crosswords(&mut self, gaddag: &Gaddag) {
+        for x in 0..self.size {
+            for y in 0..self.size {
+                self.update_crossword(x, y, gaddag);
+            }
+        }
+    }
+
+    pub fn update_around(&mut self, changed: &[(usize, usize)], gaddag: &Gaddag) {
+        // Met à jour les ancres et les mots croisés après la pose ou le retrait des jetons des
+        // cases changed, sans recalculer toute la grille
+        let mut touched = Vec::new();
+        for &(i, j) in changed {
+            touched.push((i, j));
+            touched.extend([
+                (i.wrapping_sub(1), j),
+                (i + 1, j),
+                (i, j.wrapping_sub(1)),
+                (i, j + 1),
+            ]);
+            // Les mots croisés sont verticaux : les cases au bout de la colonne de lettres
+            // qui passe par (i, j) changent aussi
+            let mut k = i;
+            while k > 0 && !self.is_empty(k - 1, j) {
+                k -= 1;
+            }
+            touched.push((k.wrapping_sub(1), j));
+            let mut k = i;
+            while !self.is_empty(k + 1, j) {
+                k += 1;
             }
+            touched.push((k + 1, j));
+        }
+        touched.retain(|&(i, j)| i < self.size && j < self.size);
+        touched.sort_unstable();
+        touched.dedup();
+        for &(i, j) in &touched {
+            self.update_anchor(i, j);
+        }
+        if self.anchors.iter().all(|row| row.iter().all(|&a| !a)) {
+            // Activation de l'ancre de départ si la grille est vide
+            let (i, j) = self.rules.start;
+            self.anchors[i][j] = true;
+        }
+        for (i, j) in touched {
+            self.update_crossword(i, j, gaddag);
         }
     }
 }
 
+impl Default for Grid {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl fmt::Display for Grid {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // Print the grid
-        writeln!(f, "Grid:")?;
-        for i in 0..self.squares.len() {
-            for j in 0..self.squares[i].len() {
-                let square = &self.squares[i][j];
-                write!(
-                    f,
-                    "{} ",
-                    match square {
-                        Square::Letter(c) => c.to_string(),
-                        _ => ".".to_string(),
-                    }
-                )?;
-            }
-            writeln!(f)?;
-        }
-        // Print the anchors
-        writeln!(f, "\nAnchors:")?;
-        for i in 0..self.anchors.len() {
-            for j in 0..self.anchors[i].len() {
-                let anchor = self.anchors[i][j];
-                write!(f, "{} ", if anchor { "x" } else { "." })?;
-            }
-            writeln!(f)?;
-        }
-        // Print the crosswords
-        writeln!(f, "\nCrosswords:")?;
-        for row in &self.crosswords {
-            for cell in row {
-                match cell {
-                    None => write!(f, ". ")?,
-                    Some(map) => {
-                        if map.is_empty() {
-                            write!(f, "/ ")?;
-                        } else {
-                            let random_char = map.keys().next().unwrap();
-                            write!(f, "{} ", random_char)?;
-                        }
-                    }
+        // Affiche la grille avec les marqueurs des cases bonus, comme l'export texte
+        write!(f, "{}", grid_to_text(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_gaddag() -> Gaddag {
+        Gaddag::from_words(
+            &["SAMA", "MAS", "MA", "AS", "SA", "TAS"],
+            "test",
+            &RuleSet::french(),
+        )
+    }
+
+    fn test_grid(gaddag: &Gaddag) -> Grid {
+        // Grille avec SAMA en H8
+        let mut grid = Grid::new();
+        grid.generate_grid();
+        grid.play(&Move::new(7, 7, Direction::Horizontal, "SAMA"), gaddag)
+            .unwrap();
+        grid
+    }
+
+    fn check(
+        grid: &Grid,
+        gaddag: &Gaddag,
+        row: usize,
+        col: usize,
+        direction: Direction,
+        word: &str,
+    ) -> Result<(), MoveError> {
+        grid.validate_move(&Move::new(row, col, direction, word), gaddag)
+    }
+
+    #[test]
+    fn coordinates_round_trip_on_every_square() {
+        let size = RuleSet::french().size();
+        for row in 0..size {
+            for col in 0..size {
+                for direction in [Direction::Horizontal, Direction::Vertical] {
+                    let reference = Grid::pos_to_ref((row, col), direction);
+                    assert_eq!(
+                        Grid::ref_to_pos(&reference, size),
+                        Ok(((row, col), direction)),
+                        "{}",
+                        reference
+                    );
                 }
             }
-            writeln!(f)?;
         }
+    }
 
-        Ok(())
+    #[test]
+    fn coordinates_accept_lowercase() {
+        assert_eq!(
+            Grid::ref_to_pos("h8", 15),
+            Ok(((7, 7), Direction::Horizontal))
+        );
+        assert_eq!(
+            Grid::ref_to_pos("8h", 15),
+            Ok(((7, 7), Direction::Vertical))
+        );
+    }
+
+    #[test]
+    fn coordinates_outside_the_board_are_rejected() {
+        assert_eq!(
+            Grid::ref_to_pos("0A", 15),
+            Err(CoordError::ColumnOutOfRange {
+                col: "0".to_string(),
+                size: 15
+            })
+        );
+        assert_eq!(
+            Grid::ref_to_pos("P1", 15),
+            Err(CoordError::RowOutOfRange { row: 'P', size: 15 })
+        );
+        assert_eq!(
+            Grid::ref_to_pos("A16", 15),
+            Err(CoordError::ColumnOutOfRange {
+                col: "16".to_string(),
+                size: 15
+            })
+        );
+        assert_eq!(
+            Grid::ref_to_pos("P1", 21),
+            Ok(((15, 0), Direction::Horizontal))
+        );
+    }
+
+    #[test]
+    fn malformed_coordinates_are_rejected() {
+        assert_eq!(Grid::ref_to_pos("  ", 15), Err(CoordError::Empty));
+        for reference in ["88", "H", "H8H", "HH"] {
+            assert_eq!(
+                Grid::ref_to_pos(reference, 15),
+                Err(CoordError::Malformed(reference.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn legal_moves_are_accepted() {
+        let gaddag = test_gaddag();
+        let grid = test_grid(&gaddag);
+        assert_eq!(
+            check(&grid, &gaddag, 6, 10, Direction::Vertical, "MAS"),
+            Ok(())
+        );
+        assert_eq!(
+            check(&grid, &gaddag, 8, 7, Direction::Horizontal, "AS"),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn too_short() {
+        let gaddag = test_gaddag();
+        let grid = test_grid(&gaddag);
+        assert_eq!(
+            check(&grid, &gaddag, 8, 7, Direction::Horizontal, "A"),
+            Err(MoveError::TooShort)
+        );
+    }
+
+    #[test]
+    fn invalid_letter() {
+        let gaddag = test_gaddag();
+        let grid = test_grid(&gaddag);
+        assert_eq!(
+            check(&grid, &gaddag, 8, 7, Direction::Horizontal, "A1"),
+            Err(MoveError::InvalidLetter('1'))
+        );
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let gaddag = test_gaddag();
+        let grid = test_grid(&gaddag);
+        assert_eq!(
+            check(&grid, &gaddag, 7, 13, Direction::Horizontal, "SAMA"),
+            Err(MoveError::OutOfBounds)
+        );
+    }
+
+    #[test]
+    fn conflict() {
+        let gaddag = test_gaddag();
+        let grid = test_grid(&gaddag);
+        assert_eq!(
+            check(&grid, &gaddag, 6, 7, Direction::Vertical, "MAS"),
+            Err(MoveError::Conflict {
+                row: 7,
+                col: 7,
+                on_board: "S".to_string(),
+                played: "A".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn no_tile_placed() {
+        let gaddag = test_gaddag();
+        let grid = test_grid(&gaddag);
+        assert_eq!(
+            check(&grid, &gaddag, 7, 7, Direction::Horizontal, "SAMA"),
+            Err(MoveError::NoTilePlaced)
+        );
+    }
+
+    #[test]
+    fn not_whole_word() {
+        let gaddag = test_gaddag();
+        let grid = test_grid(&gaddag);
+        assert_eq!(
+            check(&grid, &gaddag, 7, 11, Direction::Horizontal, "AS"),
+            Err(MoveError::NotWholeWord("SAMAAS".to_string()))
+        );
+    }
+
+    #[test]
+    fn center_not_covered() {
+        let gaddag = test_gaddag();
+        let mut grid = Grid::new();
+        grid.generate_grid();
+        assert_eq!(
+            check(&grid, &gaddag, 0, 0, Direction::Horizontal, "SA"),
+            Err(MoveError::CenterNotCovered)
+        );
+    }
+
+    #[test]
+    fn not_connected() {
+        let gaddag = test_gaddag();
+        let grid = test_grid(&gaddag);
+        assert_eq!(
+            check(&grid, &gaddag, 0, 0, Direction::Horizontal, "SA"),
+            Err(MoveError::NotConnected)
+        );
+    }
+
+    #[test]
+    fn invalid_word() {
+        let gaddag = test_gaddag();
+        let grid = test_grid(&gaddag);
+        assert_eq!(
+            check(&grid, &gaddag, 6, 7, Direction::Vertical, "XSX"),
+            Err(MoveError::InvalidWord("XSX".to_string()))
+        );
+    }
+
+    #[test]
+    fn invalid_crossword() {
+        let gaddag = test_gaddag();
+        let grid = test_grid(&gaddag);
+        assert_eq!(
+            check(&grid, &gaddag, 8, 7, Direction::Horizontal, "SA"),
+            Err(MoveError::InvalidCrossword {
+                row: 8,
+                col: 7,
+                word: "SS".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn play_refuses_an_illegal_move() {
+        let gaddag = test_gaddag();
+        let mut grid = test_grid(&gaddag);
+        let mv = Move::new(8, 7, Direction::Horizontal, "SA");
+        assert!(grid.play(&mv, &gaddag).is_err());
+        assert!(grid.is_empty(8, 7) && grid.is_empty(8, 8));
     }
 }
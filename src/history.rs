@@ -0,0 +1,272 @@
+use crate::gaddag::Gaddag;
+use crate::grid::{Grid, MoveError, Square};
+use crate::moves::Move;
+use crate::scoring::score_move;
+
+// Partie en cours : la grille, la liste des coups joués et ceux qui peuvent être rejoués
+pub struct GameHistory {
+    initial: Vec<Vec<Square>>,
+    grid: Grid,
+    moves: Vec<Move>,
+    // Nombre de coups de moves actuellement posés sur la grille
+    turn: usize,
+}
+
+impl GameHistory {
+    pub fn new(grid: Grid) -> Self {
+        // Démarre l'historique à partir d'une grille, qui devient le tour 0
+        GameHistory {
+            initial: grid.squares.clone(),
+            grid,
+            moves: Vec::new(),
+            turn: 0,
+        }
+    }
+
+    pub fn from_moves(grid: Grid, moves: &[Move], gaddag: &Gaddag) -> Result<Self, MoveError> {
+        // Rejoue une liste de coups, par exemple celle d'une partie générée
+        let mut history = GameHistory::new(grid);
+        for mv in moves {
+            history.play(mv.clone(), gaddag)?;
+        }
+        Ok(history)
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn into_grid(self) -> Grid {
+        self.grid
+    }
+
+    pub fn turn(&self) -> usize {
+        self.turn
+    }
+
+    pub fn moves(&self) -> &[Move] {
+        // Coups joués jusqu'au tour actuel
+        &self.moves[..self.turn]
+    }
+
+    pub fn all_moves(&self) -> &[Move] {
+        // Coups joués, y compris ceux annulés qui peuvent être rejoués
+        &self.moves
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.turn > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.turn < self.moves.len()
+    }
+
+    pub fn play(&mut self, mut mv: Move, gaddag: &Gaddag) -> Result<&Move, MoveError> {
+        // Joue un coup légal, complète ses jetons posés et son score, et oublie les coups annulés
+        let breakdown = score_move(&self.grid, &mv, gaddag)?;
+        mv.placed = self.grid.placed_tiles(&mv)?;
+        mv.score = breakdown.to_score();
+        self.grid.place(&mv, gaddag);
+        self.moves.truncate(self.turn);
+        self.moves.push(mv);
+        self.turn += 1;
+        Ok(&self.moves[self.turn - 1])
+    }
+
+    pub fn undo(&mut self, gaddag: &Gaddag) -> Option<&Move> {
+        // Retire les jetons du dernier coup et rend aux cases leur bonus d'origine
+        if !self.can_undo() {
+            return None;
+        }
+        self.turn -= 1;
+        let mv = &self.moves[self.turn];
+        let changed: Vec<(usize, usize)> = mv.placed.iter().map(|t| (t.row, t.col)).collect();
+        for &(i, j) in &changed {
+            self.grid.squares[i][j] = self.initial[i][j];
+        }
+        self.grid.update_around(&changed, gaddag);
+        Some(&self.moves[self.turn])
+    }
+
+    pub fn redo(&mut self, gaddag: &Gaddag) -> Option<&Move> {
+        // Rejoue le dernier coup annulé
+        if !self.can_redo() {
+            return None;
+        }
+        self.grid.place(&self.moves[self.turn], gaddag);
+        self.turn += 1;
+        Some(&self.moves[self.turn - 1])
+    }
+
+    pub fn goto(&mut self, turn: usize, gaddag: &Gaddag) -> bool {
+        // Replace la grille au tour demandé, 0 étant la grille de départ
+        if turn > self.moves.len() {
+            return false;
+        }
+        while self.turn > turn {
+            self.undo(gaddag);
+        }
+        while self.turn < turn {
+            self.redo(gaddag);
+        }
+        true
+    }
+
+    pub fn turn_of(&self, i: usize, j: usize) -> Option<usize> {
+        // Retourne le tour (à partir de 1) où le jeton de la case (i, j) a été posé
+        self.moves()
+            .iter()
+            .position(|mv| mv.placed.iter().any(|t| (t.row, t.col) == (i, j)))
+            .map(|k| k + 1)
+    }
+
+    pub fn initial_square(&self, i: usize, j: usize) -> Square {
+        // Case de la grille de départ, utile pour savoir quel bonus un jeton a recouvert
+        self.initial[i][j]
+    }
+
+    pub fn premium_used(&self, i: usize, j: usize) -> bool {
+        // Vrai si une case bonus a déjà été recouverte par un coup
+        !matches!(self.initial[i][j], Square::Blank | Square::Letter(_))
+            && self.turn_of(i, j).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::Direction;
+    use crate::rules::RuleSet;
+
+    fn test_gaddag() -> Gaddag {
+        Gaddag::from_words(
+            &[
+                "SAMA", "MAS", "AS", "SA", "TAS", "ET", "SAS", "ASAS", "ASASE",
+            ],
+            "test",
+            &RuleSet::french(),
+        )
+    }
+
+    fn empty_history() -> GameHistory {
+        let mut grid = Grid::new();
+        grid.generate_grid();
+        GameHistory::new(grid)
+    }
+
+    fn moves() -> Vec<Move> {
+        vec![
+            Move::new(7, 7, Direction::Horizontal, "SAMA"),
+            Move::new(6, 10, Direction::Vertical, "MAS"),
+            Move::new(8, 7, Direction::Horizontal, "AS"),
+        ]
+    }
+
+    fn assert_consistent(history: &GameHistory, gaddag: &Gaddag) {
+        // Les ancres et les mots croisés mis à jour case par case sont ceux d'un recalcul complet
+        let mut full = Grid::new();
+        full.squares = history.grid().squares.clone();
+        full.update_anchors();
+        full.update_crosswords(gaddag);
+        assert_eq!(history.grid().anchors, full.anchors);
+        assert_eq!(history.grid().crosswords, full.crosswords);
+    }
+
+    #[test]
+    fn play_fills_in_the_tiles_and_the_score() {
+        let gaddag = test_gaddag();
+        let history = GameHistory::from_moves(empty_history().grid, &moves(), &gaddag).unwrap();
+        assert_eq!(history.turn(), 3);
+        let last = &history.moves()[2];
+        assert_eq!(last.placed.len(), 2);
+        assert_eq!(last.score.total(), 8);
+        assert_eq!(history.turn_of(6, 10), Some(2));
+        assert_eq!(history.turn_of(0, 0), None);
+        assert!(history.premium_used(7, 7));
+        assert_consistent(&history, &gaddag);
+    }
+
+    #[test]
+    fn illegal_moves_are_refused() {
+        let gaddag = test_gaddag();
+        let mut history = empty_history();
+        let mv = Move::new(0, 0, Direction::Horizontal, "SA");
+        assert_eq!(
+            history.play(mv, &gaddag).err(),
+            Some(MoveError::CenterNotCovered)
+        );
+        assert_eq!(history.turn(), 0);
+    }
+
+    #[test]
+    fn undo_and_redo_restore_the_board() {
+        let gaddag = test_gaddag();
+        let mut history = empty_history();
+        for mv in moves() {
+            history.play(mv, &gaddag).unwrap();
+        }
+        let squares = history.grid().squares.clone();
+        assert_eq!(
+            history.undo(&gaddag).map(|mv| mv.word.clone()),
+            Some("AS".to_string())
+        );
+        assert!(history.grid().is_empty(8, 7));
+        assert!(matches!(history.grid().squares[8][8], Square::LCD));
+        assert_consistent(&history, &gaddag);
+        assert!(history.can_redo());
+        assert_eq!(
+            history.redo(&gaddag).map(|mv| mv.word.clone()),
+            Some("AS".to_string())
+        );
+        assert!(!history.can_redo());
+        assert_eq!(history.grid().squares, squares);
+        assert_consistent(&history, &gaddag);
+    }
+
+    #[test]
+    fn undo_back_to_the_empty_board() {
+        let gaddag = test_gaddag();
+        let mut history = empty_history();
+        history.play(moves()[0].clone(), &gaddag).unwrap();
+        assert!(history.undo(&gaddag).is_some());
+        assert!(history.undo(&gaddag).is_none());
+        assert!(history.grid().anchors[7][7]);
+        assert_consistent(&history, &gaddag);
+    }
+
+    #[test]
+    fn goto_any_turn() {
+        let gaddag = test_gaddag();
+        let mut history = GameHistory::from_moves(empty_history().grid, &moves(), &gaddag).unwrap();
+        for turn in [0, 2, 1, 3, 0, 3] {
+            assert!(history.goto(turn, &gaddag));
+            assert_eq!(history.turn(), turn);
+            assert_eq!(history.moves().len(), turn);
+            assert_consistent(&history, &gaddag);
+        }
+        assert!(!history.goto(4, &gaddag));
+        assert_eq!(history.turn(), 3);
+    }
+
+    #[test]
+    fn playing_after_undo_drops_the_redo_branch() {
+        let gaddag = test_gaddag();
+        let mut history = GameHistory::from_moves(empty_history().grid, &moves(), &gaddag).unwrap();
+        history.goto(1, &gaddag);
+        assert_eq!(history.all_moves().len(), 3);
+        history
+            .play(Move::new(7, 7, Direction::Vertical, "SAS"), &gaddag)
+            .unwrap();
+        assert_eq!(history.turn(), 2);
+        assert_eq!(history.all_moves().len(), 2);
+        assert!(!history.can_redo());
+        assert!(history.redo(&gaddag).is_none());
+        assert_consistent(&history, &gaddag);
+        // Un jeton posé au-dessus d'une colonne de lettres change le mot croisé sous elle
+        history
+            .play(Move::new(6, 7, Direction::Vertical, "ASAS"), &gaddag)
+            .unwrap();
+        assert_consistent(&history, &gaddag);
+    }
+}
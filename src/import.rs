@@ -0,0 +1,322 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::gaddag::Gaddag;
+use crate::grid::{Grid, MoveError, Square};
+use crate::history::GameHistory;
+use crate::moves::{Move, ParseMoveError};
+use crate::rules::{marker_square, Letter, RuleSet};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportError {
+    RowCount {
+        found: usize,
+        expected: usize,
+    },
+    ColumnCount {
+        row: usize,
+        found: usize,
+        expected: usize,
+    },
+    InvalidCharacter {
+        row: usize,
+        col: usize,
+        c: char,
+    },
+    Notation {
+        line: usize,
+        error: ParseMoveError,
+    },
+    IllegalMove {
+        line: usize,
+        mv: String,
+        error: MoveError,
+    },
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::RowCount { found, expected } => {
+                write!(f, "the board has {} rows instead of {}", found, expected)
+            }
+            ImportError::ColumnCount {
+                row,
+                found,
+                expected,
+            } => write!(
+                f,
+                "row {} has {} cells instead of {}",
+                row + 1,
+                found,
+                expected
+            ),
+            ImportError::InvalidCharacter { row, col, c } => write!(
+                f,
+                "invalid character '{}' at row {}, column {}",
+                c,
+                row + 1,
+                col + 1
+            ),
+            ImportError::Notation { line, error } => write!(f, "line {}: {}", line, error),
+            ImportError::IllegalMove { line, mv, error } => {
+                write!(f, "line {}: {} is illegal: {}", line, mv, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+pub(crate) fn content_lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    // Parcourt les lignes utiles avec leur numéro, sans les commentaires ni les lignes vides
+    text.lines()
+        .enumerate()
+        .map(|(k, line)| (k + 1, line.split('#').next().unwrap_or("").trim()))
+        .filter(|(_, line)| !line.is_empty())
+}
+
+pub fn grid_from_text(
+    text: &str,
+    rules: &Arc<RuleSet>,
+    gaddag: &Gaddag,
+) -> Result<Grid, ImportError> {
+    // Lit une grille d'autant de lignes et de cases que celle des règles : '.' pour une case
+    // vide, une lettre en majuscules pour un jeton et en minuscules pour un joker ; les
+    // marqueurs de cases bonus de l'export texte sont ignorés, les bonus étant replacés d'office.
+    // Les espaces entre les cases sont facultatifs, des caractères collés étant découpés en
+    // prenant toujours la lettre la plus longue, CH plutôt que C puis H
+    let mut rows = Vec::new();
+    for (i, (_, line)) in content_lines(text).enumerate() {
+        let mut cells: Vec<Option<Letter>> = Vec::new();
+        for chunk in line.split_whitespace() {
+            let mut rest = chunk;
+            while let Some(c) = rest.chars().next() {
+                if marker_square(c).is_some() {
+                    cells.push(None);
+                    rest = &rest[c.len_utf8()..];
+                } else if let Some((letter, len)) = rules.read_letter(rest) {
+                    cells.push(Some(letter));
+                    rest = &rest[len..];
+                } else {
+                    let col = cells.len();
+                    return Err(ImportError::InvalidCharacter { row: i, col, c });
+                }
+            }
+        }
+        rows.push(cells);
+    }
+    let size = rules.size();
+    if rows.len() != size {
+        return Err(ImportError::RowCount {
+            found: rows.len(),
+            expected: size,
+        });
+    }
+    for (i, cells) in rows.iter().enumerate() {
+        if cells.len() != size {
+            return Err(ImportError::ColumnCount {
+                row: i,
+                found: cells.len(),
+                expected: size,
+            });
+        }
+    }
+    Ok(grid_from_letters(&rows, rules, gaddag))
+}
+
+pub(crate) fn grid_from_letters(
+    letters: &[Vec<Option<Letter>>],
+    rules: &Arc<RuleSet>,
+    gaddag: &Gaddag,
+) -> Grid {
+    // Pose les lettres sur une grille neuve, puis calcule ses ancres et ses mots croisés
+    let mut grid = Grid::with_rules(rules.clone());
+    grid.generate_grid();
+    for (i, row) in letters.iter().enumerate() {
+        for (j, letter) in row.iter().enumerate() {
+            if let Some(letter) = letter {
+                grid.squares[i][j] = Square::Letter(*letter);
+            }
+        }
+    }
+    grid.update_anchors();
+    grid.update_crosswords(gaddag);
+    grid
+}
+
+pub fn parse_moves(text: &str, size: usize) -> Result<Vec<(usize, Move)>, ImportError> {
+    // Lit une liste de coups, un par ligne, comme "H8 KALIS" ou "8H kALIS 28", sur une
+    // grille de size cases de côté ; ce qui suit le mot (un score par exemple) est ignoré
+    content_lines(text)
+        .map(|(line, content)| {
+            let notation: Vec<&str> = content.split_whitespace().take(2).collect();
+            Move::parse(&notation.join(" "), size)
+                .map(|mv| (line, mv))
+                .map_err(|error| ImportError::Notation { line, error })
+        })
+        .collect()
+}
+
+pub fn history_from_moves(
+    text: &str,
+    rules: &Arc<RuleSet>,
+    gaddag: &Gaddag,
+) -> Result<GameHistory, ImportError> {
+    // Rejoue une liste de coups sur une grille vide en vérifiant chacun d'eux
+    let mut grid = Grid::with_rules(rules.clone());
+    grid.generate_grid();
+    let mut history = GameHistory::new(grid);
+    for (line, mv) in parse_moves(text, rules.size())? {
+        let notation = mv.to_string();
+        history
+            .play(mv, gaddag)
+            .map_err(|error| ImportError::IllegalMove {
+                line,
+                mv: notation,
+                error,
+            })?;
+    }
+    Ok(history)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::CoordError;
+
+    fn test_gaddag() -> Gaddag {
+        Gaddag::from_words(&["SAMA", "MAS", "AS", "SA"], "test", &RuleSet::french())
+    }
+
+    fn board_text(rows: &[(usize, &str)]) -> String {
+        // Grille vide où certaines lignes sont remplacées
+        let mut lines = vec![".".repeat(15); 15];
+        for &(i, row) in rows {
+            lines[i] = row.to_string();
+        }
+        lines.join("\n")
+    }
+
+    #[test]
+    fn board_with_letters_blanks_and_comments() {
+        let gaddag = test_gaddag();
+        let text = format!(
+            "# Partie du jour\n\n{}",
+            board_text(&[(7, ". . . . . . . S a M A . . . .  # ligne H")])
+        );
+        let rules = RuleSet::french();
+        let grid = grid_from_text(&text, &rules, &gaddag).unwrap();
+        let text = |letter: Option<Letter>| letter.map(|letter| rules.letter_text(letter));
+        assert_eq!(text(grid.letter(7, 7)).as_deref(), Some("S"));
+        assert_eq!(text(grid.letter(7, 8)).as_deref(), Some("a"));
+        assert_eq!(grid.letter(7, 6), None);
+        assert!(matches!(grid.squares[0][0], Square::MCT));
+        assert!(grid.anchors[6][8] && !grid.anchors[7][7]);
+        assert!(grid.crosswords[8][7].is_some());
+    }
+
+    #[test]
+    fn board_size_and_characters_are_checked() {
+        let gaddag = test_gaddag();
+        let text = board_text(&[]);
+        let short: Vec<&str> = text.lines().take(14).collect();
+        assert_eq!(
+            grid_from_text(&short.join("\n"), &RuleSet::french(), &gaddag).err(),
+            Some(ImportError::RowCount {
+                found: 14,
+                expected: 15
+            })
+        );
+        assert_eq!(
+            grid_from_text(&board_text(&[(3, "....")]), &RuleSet::french(), &gaddag).err(),
+            Some(ImportError::ColumnCount {
+                row: 3,
+                found: 4,
+                expected: 15
+            })
+        );
+        assert_eq!(
+            grid_from_text(
+                &board_text(&[(2, "..*............")]),
+                &RuleSet::french(),
+                &gaddag
+            )
+            .err(),
+            Some(ImportError::InvalidCharacter {
+                row: 2,
+                col: 2,
+                c: '*'
+            })
+        );
+    }
+
+    #[test]
+    fn move_list_ignores_scores_and_comments() {
+        let moves = parse_moves("# Partie\nH8 SAMA 10\n\n11G mas  # vertical\n", 15).unwrap();
+        let moves: Vec<(usize, String)> = moves
+            .into_iter()
+            .map(|(line, mv)| (line, mv.to_string()))
+            .collect();
+        assert_eq!(
+            moves,
+            [(2, "H8 SAMA".to_string()), (4, "11G mas".to_string())]
+        );
+    }
+
+    #[test]
+    fn move_list_errors_give_the_line() {
+        assert_eq!(
+            parse_moves("H8 SAMA\nH8", 15).err(),
+            Some(ImportError::Notation {
+                line: 2,
+                error: ParseMoveError::Format("H8".to_string())
+            })
+        );
+        assert_eq!(
+            parse_moves("H8 SA-MA", 15).err(),
+            Some(ImportError::Notation {
+                line: 1,
+                error: ParseMoveError::Word("SA-MA".to_string())
+            })
+        );
+        assert_eq!(
+            parse_moves("\nP1 SAMA", 15).err(),
+            Some(ImportError::Notation {
+                line: 2,
+                error: ParseMoveError::Coordinates(CoordError::RowOutOfRange {
+                    row: 'P',
+                    size: 15
+                })
+            })
+        );
+    }
+
+    #[test]
+    fn history_replays_the_moves() {
+        let gaddag = test_gaddag();
+        let rules = RuleSet::french();
+        let history = history_from_moves("H8 SAMA\n11G MAS\n", &rules, &gaddag).unwrap();
+        assert_eq!(history.turn(), 2);
+        let letter = history.grid().letter(8, 10).unwrap();
+        assert_eq!(rules.letter_text(letter), "S");
+    }
+
+    #[test]
+    fn history_rejects_an_illegal_move() {
+        let gaddag = test_gaddag();
+        assert_eq!(
+            history_from_moves(
+                "H8 SAMA\n# coup suivant\nA1 SA\n",
+                &RuleSet::french(),
+                &gaddag
+            )
+            .err(),
+            Some(ImportError::IllegalMove {
+                line: 3,
+                mv: "A1 SA".to_string(),
+                error: MoveError::NotConnected
+            })
+        );
+    }
+}
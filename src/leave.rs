@@ -0,0 +1,236 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+
+use crate::import::content_lines;
+use crate::moves::Move;
+use crate::rack::rack_tiles;
+use crate::rules::{RuleSet, Tile};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LeaveError {
+    Io(String),
+    Format { line: usize, content: String },
+    InvalidLeave { line: usize, leave: String },
+    InvalidValue { line: usize, value: String },
+}
+
+impl fmt::Display for LeaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LeaveError::Io(error) => write!(f, "{}", error),
+            LeaveError::Format { line, content } => write!(
+                f,
+                "line {}: expected \"<leave> <value>\", got \"{}\"",
+                line, content
+            ),
+            LeaveError::InvalidLeave { line, leave } => {
+                write!(f, "line {}: invalid leave {}", line, leave)
+            }
+            LeaveError::InvalidValue { line, value } => {
+                write!(f, "line {}: invalid value {}", line, value)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LeaveError {}
+
+// Valeurs des reliquats, c'est-à-dire des lettres gardées sur le rack après un coup, en points
+// ajoutés au score pour comparer les coups ; un reliquat absent de la table vaut la somme des
+// valeurs de ses lettres prises seules, et une table vide revient à classer au score
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LeaveTable {
+    values: HashMap<Vec<Tile>, f64>,
+}
+
+pub fn compare_moves(a: &Move, b: &Move) -> Ordering {
+    // Ordre des coups par score décroissant, puis par position et par mot
+    (Reverse(a.score.total()), a.row, a.col, a.direction, &a.word).cmp(&(
+        Reverse(b.score.total()),
+        b.row,
+        b.col,
+        b.direction,
+        &b.word,
+    ))
+}
+
+impl LeaveTable {
+    pub fn new() -> Self {
+        LeaveTable::default()
+    }
+
+    pub fn from_text(text: &str, rules: &RuleSet) -> Result<Self, LeaveError> {
+        // Lit une ligne par reliquat, ses lettres puis sa valeur séparées par des espaces ou
+        // une virgule comme "?ERS,25.3", le joker étant noté '?' ; '#' commence un commentaire
+        let mut values = HashMap::new();
+        for (line, content) in content_lines(text) {
+            let mut fields = content
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|field| !field.is_empty());
+            let (Some(leave), Some(value), None) = (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(LeaveError::Format {
+                    line,
+                    content: content.to_string(),
+                });
+            };
+            let tiles = rules
+                .parse_rack(leave)
+                .map_err(|_| LeaveError::InvalidLeave {
+                    line,
+                    leave: leave.to_string(),
+                })?;
+            let value: f64 = value
+                .parse()
+                .ok()
+                .filter(|value: &f64| value.is_finite())
+                .ok_or_else(|| LeaveError::InvalidValue {
+                    line,
+                    value: value.to_string(),
+                })?;
+            values.insert(rack_tiles(&tiles), value);
+        }
+        Ok(LeaveTable { values })
+    }
+
+    pub fn load(filename: &str, rules: &RuleSet) -> Result<Self, LeaveError> {
+        let text = fs::read_to_string(filename).map_err(|e| LeaveError::Io(e.to_string()))?;
+        LeaveTable::from_text(&text, rules)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn value(&self, leave: &HashMap<Tile, usize>) -> f64 {
+        // Valeur du reliquat, ou à défaut somme des valeurs de chaque lettre
+        let key = rack_tiles(leave);
+        match self.values.get(&key) {
+            Some(&value) => value,
+            None => key
+                .iter()
+                .map(|&tile| self.values.get(&vec![tile]).copied().unwrap_or(0.0))
+                .sum(),
+        }
+    }
+
+    pub fn equity(&self, mv: &Move) -> f64 {
+        // Valeur d'un coup pour le classement : son score plus celle du reliquat
+        mv.score.total() as f64 + self.value(&mv.leave)
+    }
+
+    pub fn rank(&self, moves: &mut Vec<Move>) {
+        // Trie les coups du meilleur au moins bon ; à valeur égale, le score, la position puis
+        // le mot départagent pour que le classement soit reproductible
+        let mut ranked: Vec<(f64, Move)> = moves.drain(..).map(|m| (self.equity(&m), m)).collect();
+        ranked.sort_by(|(a_equity, a), (b_equity, b)| {
+            b_equity
+                .total_cmp(a_equity)
+                .then_with(|| compare_moves(a, b))
+        });
+        moves.extend(ranked.into_iter().map(|(_, m)| m));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::{Direction, Score};
+
+    fn table(text: &str) -> LeaveTable {
+        LeaveTable::from_text(text, &RuleSet::french()).unwrap()
+    }
+
+    fn leave(text: &str) -> HashMap<Tile, usize> {
+        RuleSet::french().parse_rack(text).unwrap()
+    }
+
+    fn scored(col: usize, word: &str, main: usize, kept: &str) -> Move {
+        let mut mv = Move::new(7, col, Direction::Horizontal, word);
+        mv.score = Score {
+            main,
+            ..Score::default()
+        };
+        mv.leave = leave(kept);
+        mv
+    }
+
+    #[test]
+    fn text_with_spaces_commas_and_comments() {
+        let leaves = table("# Valeurs\n?ers 25.3\nUUV,-12\n\n?  20  # joker seul\n");
+        assert_eq!(leaves.len(), 3);
+        assert_eq!(leaves.value(&leave("SER?")), 25.3);
+        assert_eq!(leaves.value(&leave("VUU")), -12.0);
+        assert_eq!(leaves.value(&leave("?")), 20.0);
+    }
+
+    #[test]
+    fn malformed_lines_are_reported_with_their_number() {
+        let rules = RuleSet::french();
+        assert_eq!(
+            LeaveTable::from_text("ERS 1\nERS\n", &rules),
+            Err(LeaveError::Format {
+                line: 2,
+                content: "ERS".to_string(),
+            })
+        );
+        assert_eq!(
+            LeaveTable::from_text("ERS 1 2", &rules),
+            Err(LeaveError::Format {
+                line: 1,
+                content: "ERS 1 2".to_string(),
+            })
+        );
+        assert_eq!(
+            LeaveTable::from_text("E1S 3", &rules),
+            Err(LeaveError::InvalidLeave {
+                line: 1,
+                leave: "E1S".to_string(),
+            })
+        );
+        assert_eq!(
+            LeaveTable::from_text("# ok\nERS abc", &rules),
+            Err(LeaveError::InvalidValue {
+                line: 2,
+                value: "abc".to_string(),
+            })
+        );
+        assert!(matches!(
+            LeaveTable::from_text("ERS NaN", &rules),
+            Err(LeaveError::InvalidValue { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn unknown_leaves_sum_their_single_tiles() {
+        let leaves = table("? 25\nE 3\nQ -7\nES 10\n");
+        assert_eq!(leaves.value(&leave("ES")), 10.0);
+        assert_eq!(leaves.value(&leave("EQ?")), 21.0);
+        assert_eq!(leaves.value(&leave("EEZ")), 6.0);
+        assert_eq!(leaves.value(&HashMap::new()), 0.0);
+        assert_eq!(LeaveTable::new().value(&leave("?ERS")), 0.0);
+    }
+
+    #[test]
+    fn rank_orders_by_score_plus_leave_then_by_position() {
+        let leaves = table("? 25\nUUV -15\n");
+        let mut moves = vec![
+            scored(0, "VU", 30, "UUV"),
+            scored(1, "ME", 12, "?"),
+            scored(3, "ES", 20, "A"),
+            scored(2, "SE", 20, "A"),
+        ];
+        leaves.rank(&mut moves);
+        let order: Vec<(&str, usize)> = moves.iter().map(|m| (m.word.as_str(), m.col)).collect();
+        assert_eq!(order, [("ME", 1), ("SE", 2), ("ES", 3), ("VU", 0)]);
+        LeaveTable::new().rank(&mut moves);
+        let order: Vec<&str> = moves.iter().map(|m| m.word.as_str()).collect();
+        assert_eq!(order, ["VU", "SE", "ES", "ME"]);
+    }
+}
@@ -0,0 +1,43 @@
+pub mod bag;
+pub mod constants;
+pub mod endgame;
+pub mod export;
+pub mod gaddag;
+pub mod game;
+pub mod generate;
+pub mod grid;
+pub mod history;
+pub mod import;
+pub mod leave;
+pub mod moves;
+pub mod preendgame;
+pub mod query;
+pub mod rack;
+pub mod rules;
+pub mod scoring;
+pub mod screenshot;
+pub mod simulation;
+pub mod solver;
+pub mod unseen;
+
+pub use bag::Bag;
+pub use endgame::{solve_endgame, EndgameConfig, EndgameResult};
+pub use gaddag::{Gaddag, NodeId, RejectedLine};
+pub use game::{play_classic_game, Action, Game, GameEnd, GameError, GameTurn, Player};
+pub use generate::{generate_game, Turn};
+pub use grid::{CoordError, Grid, MoveError, Square};
+pub use history::GameHistory;
+pub use leave::{LeaveError, LeaveTable};
+pub use moves::{Direction, Move, ParseMoveError, PlacedTile, Score};
+pub use preendgame::{
+    solve_pre_endgame, PreEndgameAnalysis, PreEndgameConfig, PreEndgameError, PreEndgameResult,
+};
+pub use query::{
+    anagrams, back_hooks, front_hooks, hooks, inner_hooks, parse_pattern, pattern_matches,
+    study_card, sub_anagrams, Hooks, PatternItem,
+};
+pub use rules::{Letter, RuleSet, RulesError, Tile, TileInfo, BLANK};
+pub use scoring::{score_move, LetterScore, ScoreBreakdown, WordScore};
+pub use simulation::{simulate_moves, SimConfig, SimResult};
+pub use solver::{count_solutions, generate_solutions, generate_solutions_parallel};
+pub use unseen::{unseen_tiles, Excess, Unseen};
@@ -1,81 +1,929 @@
+use std::cmp::Reverse;
 use std::collections::HashMap;
-use std::io;
-
-mod constants;
-
-mod gaddag;
-use gaddag::Gaddag;
-
-mod grid;
-use grid::Grid;
-use grid::Square;
-
-mod bag;
-
-mod solver;
-use crate::solver::generate_solutions;
-
-mod generate;
-use crate::generate::generate_game;
-
-fn main() -> io::Result<()> {
-    let gaddag = Gaddag::read_words_from_file("ODS9.txt");
-    // let mut grid = Grid::new();
-    // Grid::generate_grid(&mut grid);
-
-    // grid.play("KALIS", 7, 3, true, &gaddag);
-    // grid.play("HERON", 6, 6, true, &gaddag);
-    // grid.play("WONS", 5, 9, false, &gaddag);
-    // grid.play("EBOUTEES", 0, 7, false, &gaddag);
-    // grid.play("SUDATION", 2, 1, true, &gaddag);
-    // grid.play("AmALGAmER", 2, 4, false, &gaddag);
-    // grid.play("BALBUTIE", 1, 7, true, &gaddag);
-    // grid.play("VENT", 0, 11, true, &gaddag);
-    // grid.play("FJELL", 5, 0, true, &gaddag);
-    // grid.play("FUMEZ", 5, 0, false, &gaddag);
-
-    // grid.squares[7][3] = Square::Letter('G');
-    // grid.squares[7][4] = Square::Letter('U');
-    // grid.squares[7][5] = Square::Letter('E');
-    // grid.squares[7][6] = Square::Letter('E');
-    // grid.squares[7][7] = Square::Letter('E');
-    // grid.squares[7][8] = Square::Letter('S');
-    // grid.squares[1][6] = Square::Letter('C');
-    // grid.squares[2][6] = Square::Letter('I');
-    // grid.squares[3][6] = Square::Letter('B');
-    // grid.squares[4][6] = Square::Letter('L');
-    // grid.squares[5][6] = Square::Letter('E');
-    // grid.squares[6][6] = Square::Letter('R');
-    // grid.squares[8][6] = Square::Letter('Z');
-    // grid.squares[4][4] = Square::Letter('W');
-    // grid.squares[4][5] = Square::Letter('A');
-    // grid.squares[4][7] = Square::Letter('I');
-    // grid.squares[5][0] = Square::Letter('T');
-    // grid.squares[5][1] = Square::Letter('A');
-    // grid.squares[5][2] = Square::Letter('R');
-    // grid.squares[5][3] = Square::Letter('G');
-    // grid.squares[5][4] = Square::Letter('U');
-    // grid.squares[5][5] = Square::Letter('I');
-
-    // grid.update_anchors();
-    // grid.update_crosswords(&gaddag);
-    // println!("{}", grid);
-
-    // let rack: HashMap<char, usize> = [('E', 2), ('G', 1), ('T', 1), ('X', 1), ('Y', 1), ('A', 1)]
-    //     .iter()
-    //     .cloned()
-    //     .collect();
-    // let mut valid_words = generate_solutions(&grid, &rack, &gaddag);
-    // valid_words.sort_by(|a, b| b.score.cmp(&a.score));
-    // println!("Number of solutions: {}", valid_words.len());
-    // for validword in valid_words.iter().take(10) {
-    //     println!(
-    //         "Position: {:?}, Word: {}, Rack: {:?}, Score: {}",
-    //         validword.position, validword.word, validword.rack, validword.score
-    //     );
-    // }
-
-    generate_game(&gaddag);
+use std::fmt;
+use std::fs;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use scrabble_solver::export::{grid_to_json, grid_to_svg, grid_to_text};
+use scrabble_solver::import::{grid_from_text, history_from_moves};
+use scrabble_solver::screenshot::grid_from_screenshot;
+use scrabble_solver::{
+    anagrams, generate_game, generate_solutions_parallel, hooks, parse_pattern, pattern_matches,
+    play_classic_game, simulate_moves, solve_endgame, solve_pre_endgame, study_card, sub_anagrams,
+    unseen_tiles, Action, Bag, EndgameConfig, Gaddag, GameEnd, GameHistory, Grid, LeaveTable,
+    Letter, PreEndgameConfig, RuleSet, SimConfig, Tile, Unseen,
+};
+
+const DEFAULT_DICT: &str = "ODS9.txt";
+
+const USAGE: &str = "Usage: ScrabbleSolver <command> [options]
+
+Commands:
+  solve       --rack LETTERS [--board FILE | --moves FILE | --screenshot PNG] [--top N]
+              [--leaves FILE] [--dict FILE]
+              Lists the best moves for a rack ('?' is a blank) on a board
+  sim         --rack LETTERS [--board FILE | --moves FILE | --screenshot PNG] [--candidates K]
+              [--iterations N] [--plies P] [--spread S] [--seed S] [--leaves FILE] [--dict FILE]
+              Simulates the K best moves against random racks drawn from the unseen tiles,
+              playing P more moves, and ranks them by mean spread with its standard error and
+              the share of draws ending ahead, S being the current lead
+  endgame     --rack LETTERS [--opponent LETTERS] [--board FILE | --moves FILE | --screenshot PNG]
+              [--time SECONDS] [--depth N] [--dict FILE]
+              Finds the sequence of moves giving the best final spread once the bag is empty,
+              the opponent holding the unseen tiles unless given
+  preendgame  --rack LETTERS [--board FILE | --moves FILE | --screenshot PNG] [--candidates K]
+              [--time SECONDS] [--depth N] [--budget SECONDS] [--spread S] [--leaves FILE]
+              [--dict FILE]
+              With 1 to 6 tiles in the bag, plays the K best moves, the K best moves emptying
+              the bag and a pass against every possible draw, solving each endgame within the
+              given time and depth and each candidate within its share of the budget, and
+              ranks the moves emptying the bag apart from the estimated others, by win
+              probability and mean spread
+  unseen      [--board FILE | --moves FILE | --screenshot PNG] [--rack LETTERS] [--dict FILE]
+              Lists the tiles seen neither on the board nor on the rack, with the number of
+              vowels, consonants and blanks, and fails if a letter is seen too often
+  simulate    [--games N] [--seed S] [--leaves FILE] [--dict FILE]
+              Plays N duplicate games, always playing the move ranked first by score plus
+              leave value (by score alone without --leaves)
+  game        [--players N] [--seed S] [--leaves FILE] [--dict FILE]
+              Plays a classic game between N players (2 by default) choosing the top move by
+              score and leave, or the exchange keeping the best leave when it is worth more,
+              or passing, until a player goes out or six scoreless turns in a row, then
+              applies the final rack values
+  check       WORD... [--dict FILE]
+              Checks whether each word is in the dictionary
+  query       valid WORD... | anagram RACK | subanagram RACK [--min N]
+              | pattern PATTERN [--rack RACK] | hooks WORD... [--format line|cards]
+              [--dict FILE]
+              Looks words up for training: validity, anagrams of a whole rack, words made
+              with part of a rack, words matching a pattern where '?' is any letter and '*'
+              any number of letters, or the letters that can be added before, after or inside
+              a word, optionally as study cards
+  export      [--board FILE | --moves FILE | --screenshot PNG] [--format text|json|svg]
+              [--output FILE] [--dict FILE]
+              Exports a position as a text board, a JSON document or an SVG image
+  build-dict  --output FILE [--dict FILE]
+              Compiles a word list into a binary dictionary that loads quickly, listing the
+              lines that cannot be written with the alphabet
+  rules       [NAME | FILE] [--format toml|json]
+              Lists the bundled rule sets, or prints one as a starting point for a custom file
+
+The dictionary defaults to ODS9.txt and may be a word list or a compiled dictionary.
+Every command but rules accepts --rules NAME|FILE, a bundled rule set (french by default,
+english, spanish, german, italian, catalan, super for the 21x21 Super Scrabble) or a TOML
+or JSON file with the same fields; a compiled dictionary must use the same alphabet.
+Letters may span several characters, like the Spanish CH: words and racks are split by
+always taking the longest letter, and a blank is written in lowercase. A word list is
+normalised line by line: spaces are trimmed, words are uppercased, the replacements of the
+rule set are applied (like Œ to OE) and accents are removed from characters that are not
+part of a letter, so É becomes E while the Spanish Ñ is kept.
+A board file has one line per row of the board and one cell per column, matching the board
+size of the rule set (15 with the usual rules, 21 for super): '.' for an empty square, an
+uppercase letter for a tile, a lowercase letter for a blank; the premium markers written by
+export are accepted for empty squares. A moves file has one move per line in coordinate
+notation, like \"H8 KALIS\" or \"8H kALIS\", replayed from an empty board.
+A screenshot is read by locating the board and recognising each tile; a tile without
+a value is taken as a blank. Check the result with export before relying on it.
+A leaves file gives the value of the tiles kept on the rack after a move, one leave per line
+like \"?ERS 25.3\" or \"UUV,-12\"; moves are then ranked by score plus leave value, a leave
+missing from the file being worth the sum of its single tiles.";
+
+enum CliError {
+    Usage(String),
+    Failure(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(message) => write!(f, "{}\n\n{}", message, USAGE),
+            CliError::Failure(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+}
+
+impl Args {
+    fn parse(raw: &[String], allowed: &[&str]) -> Result<Args, CliError> {
+        // Sépare les options "--nom valeur" des arguments positionnels
+        let mut positional = Vec::new();
+        let mut options = HashMap::new();
+        let mut iter = raw.iter();
+        while let Some(arg) = iter.next() {
+            if let Some(name) = arg.strip_prefix("--") {
+                if !allowed.contains(&name) {
+                    return Err(CliError::Usage(format!("Unknown option: {}", arg)));
+                }
+                let Some(value) = iter.next() else {
+                    return Err(CliError::Usage(format!("Missing value for {}", arg)));
+                };
+                options.insert(name.to_string(), value.clone());
+            } else {
+                positional.push(arg.clone());
+            }
+        }
+        Ok(Args {
+            positional,
+            options,
+        })
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+
+    fn require(&self, name: &str) -> Result<&str, CliError> {
+        self.get(name)
+            .ok_or_else(|| CliError::Usage(format!("Missing required option --{}", name)))
+    }
+
+    fn number<T: std::str::FromStr>(&self, name: &str, default: T) -> Result<T, CliError> {
+        match self.get(name) {
+            Some(value) => value
+                .parse()
+                .map_err(|_| CliError::Usage(format!("Invalid value for --{}: {}", name, value))),
+            None => Ok(default),
+        }
+    }
+
+    fn dict(&self) -> &str {
+        self.get("dict").unwrap_or(DEFAULT_DICT)
+    }
+}
+
+fn load_gaddag(filename: &str, rules: &RuleSet) -> Result<Gaddag, CliError> {
+    let gaddag = Gaddag::open(filename, rules)
+        .map_err(|e| CliError::Failure(format!("Cannot read dictionary {}: {}", filename, e)))?;
+    // Les lignes écartées d'une liste brute sont signalées sans interrompre la commande
+    if !gaddag.rejected().is_empty() {
+        eprintln!(
+            "Warning: {} lines of {} were rejected, see build-dict for details",
+            gaddag.rejected().len(),
+            filename
+        );
+    }
+    Ok(gaddag)
+}
+
+fn load_rules(args: &Args) -> Result<Arc<RuleSet>, CliError> {
+    let name = args.get("rules").unwrap_or("french");
+    RuleSet::load(name)
+        .map(Arc::new)
+        .map_err(|e| CliError::Failure(format!("Cannot load rules {}: {}", name, e)))
+}
+
+fn load_leaves(args: &Args, rules: &RuleSet) -> Result<Option<LeaveTable>, CliError> {
+    let Some(filename) = args.get("leaves") else {
+        return Ok(None);
+    };
+    LeaveTable::load(filename, rules)
+        .map(Some)
+        .map_err(|e| CliError::Failure(format!("Cannot read leaves {}: {}", filename, e)))
+}
+
+fn parse_rack(letters: &str, rules: &RuleSet) -> Result<HashMap<Tile, usize>, CliError> {
+    // Transforme une chaîne comme "EGTXYA?" en rack
+    let rack = rules
+        .parse_rack(letters)
+        .map_err(|c| CliError::Usage(format!("Invalid rack letter: {}", c)))?;
+    if rack.is_empty() {
+        return Err(CliError::Usage("The rack is empty".to_string()));
+    }
+    Ok(rack)
+}
+
+fn read_file(filename: &str) -> Result<String, CliError> {
+    fs::read_to_string(filename)
+        .map_err(|e| CliError::Failure(format!("Cannot read {}: {}", filename, e)))
+}
+
+fn load_grid(args: &Args, rules: &Arc<RuleSet>, gaddag: &Gaddag) -> Result<Grid, CliError> {
+    // Construit la grille depuis --board, la liste de coups --moves, la capture d'écran
+    // --screenshot, ou vide
+    let invalid = |filename: &str, e: &dyn std::error::Error| {
+        CliError::Failure(format!("Invalid position in {}: {}", filename, e))
+    };
+    let sources = ["board", "moves", "screenshot"];
+    let given: Vec<&str> = sources
+        .into_iter()
+        .filter(|s| args.get(s).is_some())
+        .collect();
+    if given.len() > 1 {
+        return Err(CliError::Usage(
+            "Only one of --board, --moves and --screenshot can be used".to_string(),
+        ));
+    }
+    match given.first().map(|&s| (s, args.get(s).unwrap_or_default())) {
+        Some(("board", filename)) => {
+            grid_from_text(&read_file(filename)?, rules, gaddag).map_err(|e| invalid(filename, &e))
+        }
+        Some(("moves", filename)) => history_from_moves(&read_file(filename)?, rules, gaddag)
+            .map(GameHistory::into_grid)
+            .map_err(|e| invalid(filename, &e)),
+        Some((_, filename)) => {
+            grid_from_screenshot(filename, rules, gaddag).map_err(|e| invalid(filename, &e))
+        }
+        None => {
+            let mut grid = Grid::with_rules(rules.clone());
+            grid.generate_grid();
+            Ok(grid)
+        }
+    }
+}
+
+fn solve(args: &Args) -> Result<(), CliError> {
+    let rules = load_rules(args)?;
+    let rack = parse_rack(args.require("rack")?, &rules)?;
+    let top: usize = args.number("top", 10)?;
+    let leaves = load_leaves(args, &rules)?;
+    let gaddag = load_gaddag(args.dict(), &rules)?;
+    let grid = load_grid(args, &rules, &gaddag)?;
+    let mut valid_words = generate_solutions_parallel(&grid, &rack, &gaddag);
+    println!("Number of solutions: {}", valid_words.len());
+    match leaves {
+        // Avec une table de reliquats, les coups sont classés selon leur valeur
+        Some(leaves) => {
+            leaves.rank(&mut valid_words);
+            for validword in valid_words.iter().take(top) {
+                println!(
+                    "{:<4} {:<15} {:>4} {:>7.1}  {}",
+                    validword.coordinates(),
+                    validword.word,
+                    validword.score.total(),
+                    leaves.equity(validword),
+                    rules.rack_text(&validword.leave)
+                );
+            }
+        }
+        None => {
+            valid_words.sort_by_key(|w| Reverse(w.score.total()));
+            for validword in valid_words.iter().take(top) {
+                println!(
+                    "{:<4} {:<15} {:>4}  {}",
+                    validword.coordinates(),
+                    validword.word,
+                    validword.score.total(),
+                    rules.rack_text(&validword.leave)
+                );
+            }
+        }
+    }
     Ok(())
 }
+
+fn impossible(rules: &RuleSet, unseen: &Unseen) -> CliError {
+    // Décrit les lettres en trop d'une position impossible
+    let letters: Vec<String> = unseen
+        .excess
+        .iter()
+        .map(|excess| {
+            format!(
+                "{} {} seen but only {} in the game",
+                excess.seen,
+                rules.name(excess.tile),
+                excess.count
+            )
+        })
+        .collect();
+    CliError::Failure(format!("Impossible position: {}", letters.join(", ")))
+}
+
+fn unseen(args: &Args) -> Result<(), CliError> {
+    let rules = load_rules(args)?;
+    let rack = match args.get("rack") {
+        Some(letters) => parse_rack(letters, &rules)?,
+        None => HashMap::new(),
+    };
+    let gaddag = load_gaddag(args.dict(), &rules)?;
+    let grid = load_grid(args, &rules, &gaddag)?;
+    let unseen = unseen_tiles(&grid, &rack);
+    println!(
+        "Unseen tiles ({}): {}",
+        unseen.len(),
+        rules.rack_text(&unseen.tiles)
+    );
+    println!(
+        "Vowels: {}, consonants: {}, blanks: {}",
+        unseen.vowels,
+        unseen.consonants,
+        unseen.blanks()
+    );
+    if unseen.is_possible() {
+        Ok(())
+    } else {
+        Err(impossible(&rules, &unseen))
+    }
+}
+
+fn endgame(args: &Args) -> Result<(), CliError> {
+    let rules = load_rules(args)?;
+    let rack = parse_rack(args.require("rack")?, &rules)?;
+    let defaults = EndgameConfig::default();
+    let seconds = args.number("time", defaults.time_limit.as_secs_f64())?;
+    let config = EndgameConfig {
+        time_limit: Duration::try_from_secs_f64(seconds)
+            .map_err(|_| CliError::Usage(format!("Invalid value for --time: {}", seconds)))?,
+        max_depth: args.number("depth", defaults.max_depth)?,
+    };
+    let gaddag = load_gaddag(args.dict(), &rules)?;
+    let grid = load_grid(args, &rules, &gaddag)?;
+    let unseen = unseen_tiles(&grid, &rack);
+    if !unseen.is_possible() {
+        return Err(impossible(&rules, &unseen));
+    }
+    // Sans --opponent, le sac étant vide, l'adversaire a tous les jetons invisibles
+    let opponent = match args.get("opponent") {
+        Some(letters) => parse_rack(letters, &rules)?,
+        None if unseen.len() <= rules.rack_size => unseen.tiles.clone(),
+        None => {
+            return Err(CliError::Failure(format!(
+                "The bag is not empty: {} tiles are unseen",
+                unseen.len()
+            )))
+        }
+    };
+    let start = Instant::now();
+    let result = solve_endgame(&grid, &rack, &opponent, &gaddag, &config);
+    println!(
+        "{} against {}: spread {:+} ({}, depth {}, {} nodes, {:?})",
+        rules.rack_text(&rack),
+        rules.rack_text(&opponent),
+        result.spread,
+        if result.exact { "exact" } else { "time limit" },
+        result.depth,
+        result.nodes,
+        start.elapsed()
+    );
+    for (k, action) in result.pv.iter().enumerate() {
+        let player = if k % 2 == 0 { "us" } else { "them" };
+        match action {
+            Some(mv) => println!(
+                "{:>2}. {:<4} {:<4} {:<15} {:>4}",
+                k + 1,
+                player,
+                mv.coordinates(),
+                mv.word,
+                mv.score.total()
+            ),
+            None => println!("{:>2}. {:<4} pass", k + 1, player),
+        }
+    }
+    Ok(())
+}
+
+fn preendgame(args: &Args) -> Result<(), CliError> {
+    let rules = load_rules(args)?;
+    let rack = parse_rack(args.require("rack")?, &rules)?;
+    let defaults = PreEndgameConfig::default();
+    let seconds = args.number("time", defaults.endgame.time_limit.as_secs_f64())?;
+    let budget = args.number("budget", defaults.time_limit.as_secs_f64())?;
+    let config = PreEndgameConfig {
+        candidates: args.number("candidates", defaults.candidates)?,
+        endgame: EndgameConfig {
+            time_limit: Duration::try_from_secs_f64(seconds)
+                .map_err(|_| CliError::Usage(format!("Invalid value for --time: {}", seconds)))?,
+            max_depth: args.number("depth", defaults.endgame.max_depth)?,
+        },
+        time_limit: Duration::try_from_secs_f64(budget)
+            .map_err(|_| CliError::Usage(format!("Invalid value for --budget: {}", budget)))?,
+        spread: args.number("spread", defaults.spread)?,
+    };
+    let leaves = load_leaves(args, &rules)?.unwrap_or_default();
+    let gaddag = load_gaddag(args.dict(), &rules)?;
+    let grid = load_grid(args, &rules, &gaddag)?;
+    let start = Instant::now();
+    let analysis = solve_pre_endgame(&grid, &rack, &gaddag, &leaves, &config)
+        .map_err(|e| CliError::Failure(format!("Cannot analyse the position: {}", e)))?;
+    println!(
+        "{} candidates ({:?})",
+        analysis.emptying.len() + analysis.keeping.len(),
+        start.elapsed()
+    );
+    // Les deux groupes sont affichés séparément, leurs évaluations ne se comparant pas
+    let groups = [
+        (
+            "Moves emptying the bag, endgames solved for every draw:",
+            &analysis.emptying,
+        ),
+        (
+            "Moves keeping tiles in the bag, estimated:",
+            &analysis.keeping,
+        ),
+    ];
+    for (title, results) in groups {
+        if results.is_empty() {
+            continue;
+        }
+        println!("{}", title);
+        for result in results {
+            let (coordinates, word, score, leave) = match &result.mv {
+                Some(mv) => (
+                    mv.coordinates(),
+                    mv.word.clone(),
+                    mv.score.total(),
+                    rules.rack_text(&mv.leave),
+                ),
+                None => (String::new(), "pass".to_string(), 0, rules.rack_text(&rack)),
+            };
+            println!(
+                "  {:<4} {:<15} {:>4} {:>5.1}% {:>+8.1} {:>5} draws {:<6}  {}",
+                coordinates,
+                word,
+                score,
+                100.0 * result.win_rate,
+                result.spread,
+                result.draws,
+                if result.exact { "exact" } else { "approx" },
+                leave
+            );
+        }
+    }
+    Ok(())
+}
+
+fn sim(args: &Args) -> Result<(), CliError> {
+    let rules = load_rules(args)?;
+    let rack = parse_rack(args.require("rack")?, &rules)?;
+    let defaults = SimConfig::default();
+    let config = SimConfig {
+        candidates: args.number("candidates", defaults.candidates)?,
+        iterations: args.number("iterations", defaults.iterations)?,
+        plies: args.number("plies", defaults.plies)?,
+        spread: args.number("spread", defaults.spread)?,
+        seed: match args.get("seed") {
+            Some(_) => Some(args.number("seed", 0)?),
+            None => None,
+        },
+    };
+    let leaves = load_leaves(args, &rules)?.unwrap_or_default();
+    let gaddag = load_gaddag(args.dict(), &rules)?;
+    let grid = load_grid(args, &rules, &gaddag)?;
+    let unseen = unseen_tiles(&grid, &rack);
+    if !unseen.is_possible() {
+        return Err(impossible(&rules, &unseen));
+    }
+    let start = Instant::now();
+    let results = simulate_moves(&grid, &rack, &gaddag, &leaves, &config);
+    println!(
+        "{} candidates, {} iterations of {} plies ({:?})",
+        results.len(),
+        config.iterations,
+        config.plies,
+        start.elapsed()
+    );
+    for result in &results {
+        println!(
+            "{:<4} {:<15} {:>4} {:>8.1} ±{:<5.1} {:>5.1}%  {}",
+            result.mv.coordinates(),
+            result.mv.word,
+            result.mv.score.total(),
+            result.equity,
+            result.std_error,
+            100.0 * result.ahead_rate,
+            rules.rack_text(&result.mv.leave)
+        );
+    }
+    Ok(())
+}
+
+fn simulate(args: &Args) -> Result<(), CliError> {
+    let games: u64 = args.number("games", 1)?;
+    let seed: Option<u64> = match args.get("seed") {
+        Some(_) => Some(args.number("seed", 0)?),
+        None => None,
+    };
+    let rules = load_rules(args)?;
+    let leaves = load_leaves(args, &rules)?.unwrap_or_default();
+    let gaddag = load_gaddag(args.dict(), &rules)?;
+    for game in 0..games {
+        let start = Instant::now();
+        let mut bag = Bag::with_rules(rules.clone(), seed.map(|seed| seed.wrapping_add(game)));
+        let turns = generate_game(&gaddag, &mut bag, &leaves);
+        println!("Game {}", game + 1);
+        for (moves_nb, turn) in turns.iter().enumerate() {
+            println!(
+                "Move: {}, Rack: {}, Position: {}, Word: {}, Score: {}",
+                moves_nb,
+                rules.rack_text(&turn.rack),
+                turn.top.coordinates(),
+                turn.top.word,
+                turn.top.score.total()
+            );
+        }
+        let total: usize = turns.iter().map(|turn| turn.top.score.total()).sum();
+        println!(
+            "Total: {} points in {} moves ({:?})\n",
+            total,
+            turns.len(),
+            start.elapsed()
+        );
+    }
+    Ok(())
+}
+
+fn game(args: &Args) -> Result<(), CliError> {
+    let players: usize = args.number("players", 2)?;
+    if players < 2 {
+        return Err(CliError::Usage(
+            "A game needs at least 2 players".to_string(),
+        ));
+    }
+    let seed: Option<u64> = match args.get("seed") {
+        Some(_) => Some(args.number("seed", 0)?),
+        None => None,
+    };
+    let rules = load_rules(args)?;
+    let leaves = load_leaves(args, &rules)?.unwrap_or_default();
+    let gaddag = load_gaddag(args.dict(), &rules)?;
+    let start = Instant::now();
+    let game = play_classic_game(&gaddag, rules.clone(), players, &leaves, seed)
+        .map_err(|e| CliError::Failure(format!("Cannot play the game: {}", e)))?;
+    let mut scores = vec![0; players];
+    for (turns_nb, turn) in game.turns.iter().enumerate() {
+        scores[turn.player] += turn.score;
+        let action = match &turn.action {
+            Action::Play(mv) => format!("{} {}", mv.coordinates(), mv.word),
+            Action::Exchange(tiles) => {
+                let mut exchanged = HashMap::new();
+                for &tile in tiles {
+                    *exchanged.entry(tile).or_insert(0) += 1;
+                }
+                format!("exchange {}", rules.rack_text(&exchanged))
+            }
+            Action::Pass => "pass".to_string(),
+        };
+        println!(
+            "Turn: {}, Player: {}, Rack: {}, Action: {}, Score: {}, Total: {}",
+            turns_nb + 1,
+            turn.player + 1,
+            rules.rack_text(&turn.rack),
+            action,
+            turn.score,
+            scores[turn.player]
+        );
+    }
+    match &game.end {
+        Some(GameEnd::Out { player, .. }) => println!("Player {} went out", player + 1),
+        Some(GameEnd::ZeroTurns { .. }) => println!("Six scoreless turns in a row"),
+        None => {}
+    }
+    let adjustments = game.end.as_ref().map_or(&[][..], |end| end.adjustments());
+    for (k, player) in game.players.iter().enumerate() {
+        let adjustment = adjustments.get(k).copied().unwrap_or(0);
+        if player.rack.is_empty() {
+            println!(
+                "Player {}: {} points ({:+})",
+                k + 1,
+                player.score,
+                adjustment
+            );
+        } else {
+            println!(
+                "Player {}: {} points ({:+} for {})",
+                k + 1,
+                player.score,
+                adjustment,
+                rules.rack_text(&player.rack)
+            );
+        }
+    }
+    let winners: Vec<String> = game.winners().iter().map(|k| (k + 1).to_string()).collect();
+    println!(
+        "Winner: player {} in {} turns ({:?})",
+        winners.join(", player "),
+        game.turns.len(),
+        start.elapsed()
+    );
+    Ok(())
+}
+
+fn check(args: &Args) -> Result<(), CliError> {
+    if args.positional.is_empty() {
+        return Err(CliError::Usage("No word to check".to_string()));
+    }
+    let rules = load_rules(args)?;
+    let gaddag = load_gaddag(args.dict(), &rules)?;
+    let mut all_valid = true;
+    for word in &args.positional {
+        let word = rules.normalize(word);
+        let valid = rules
+            .parse_tiles(&word)
+            .is_ok_and(|tiles| gaddag.is_valid_word(&tiles));
+        println!("{}: {}", word, if valid { "valid" } else { "invalid" });
+        all_valid &= valid;
+    }
+    if all_valid {
+        Ok(())
+    } else {
+        Err(CliError::Failure(
+            "Some words are not in the dictionary".to_string(),
+        ))
+    }
+}
+
+fn print_words(rules: &RuleSet, words: &[Vec<Letter>]) {
+    // Affiche les mots trouvés par longueur, les jokers en minuscules
+    let mut start = 0;
+    while start < words.len() {
+        let length = words[start].len();
+        let end = start
+            + words[start..]
+                .iter()
+                .take_while(|w| w.len() == length)
+                .count();
+        let texts: Vec<String> = words[start..end]
+            .iter()
+            .map(|word| rules.word_text(word))
+            .collect();
+        println!("{} letters ({}): {}", length, end - start, texts.join(" "));
+        start = end;
+    }
+    println!("{} words", words.len());
+}
+
+fn query_word(rules: &RuleSet, word: &str) -> Result<Vec<Tile>, CliError> {
+    rules
+        .normalize_word(word)
+        .map_err(|c| CliError::Usage(format!("Invalid letter in {}: {}", word, c)))
+}
+
+fn query(args: &Args) -> Result<(), CliError> {
+    let Some((mode, words)) = args.positional.split_first() else {
+        return Err(CliError::Usage("Missing query".to_string()));
+    };
+    let single = || match words {
+        [word] => Ok(word.as_str()),
+        _ => Err(CliError::Usage(format!(
+            "The {} query takes one argument",
+            mode
+        ))),
+    };
+    let rules = load_rules(args)?;
+    let gaddag = load_gaddag(args.dict(), &rules)?;
+    match mode.as_str() {
+        "valid" => {
+            for word in words {
+                let tiles = query_word(&rules, word)?;
+                let valid = gaddag.is_valid_word(&tiles);
+                let text = rules.tiles_text(&tiles);
+                println!("{}: {}", text, if valid { "valid" } else { "invalid" });
+            }
+        }
+        "anagram" => print_words(&rules, &anagrams(&gaddag, &parse_rack(single()?, &rules)?)),
+        "subanagram" => {
+            let rack = parse_rack(single()?, &rules)?;
+            let min_length = args.number("min", 2)?;
+            print_words(&rules, &sub_anagrams(&gaddag, &rack, min_length));
+        }
+        "pattern" => {
+            let text = single()?;
+            let pattern = parse_pattern(&rules, text)
+                .map_err(|c| CliError::Usage(format!("Invalid letter in {}: {}", text, c)))?;
+            let rack = args
+                .get("rack")
+                .map(|letters| parse_rack(letters, &rules))
+                .transpose()?;
+            print_words(&rules, &pattern_matches(&gaddag, &pattern, rack.as_ref()));
+        }
+        "hooks" => {
+            let cards = match args.get("format").unwrap_or("line") {
+                "line" => false,
+                "cards" => true,
+                other => return Err(CliError::Usage(format!("Unknown format: {}", other))),
+            };
+            for word in words {
+                let tiles = query_word(&rules, word)?;
+                let hooks = hooks(&gaddag, &tiles);
+                if cards {
+                    println!("{}", study_card(&rules, &tiles, &hooks));
+                    continue;
+                }
+                let show = |hooks: &[Tile]| match hooks {
+                    [] => "-".to_string(),
+                    _ => rules.tiles_text(hooks),
+                };
+                let inner: Vec<String> = hooks
+                    .inner
+                    .iter()
+                    .map(|&(position, tile)| format!("{}{}", rules.name(tile), position))
+                    .collect();
+                print!(
+                    "{} {} {}",
+                    show(&hooks.front),
+                    rules.tiles_text(&tiles),
+                    show(&hooks.back)
+                );
+                if inner.is_empty() {
+                    println!();
+                } else {
+                    println!("  inner {}", inner.join(" "));
+                }
+            }
+        }
+        _ => return Err(CliError::Usage(format!("Unknown query: {}", mode))),
+    }
+    Ok(())
+}
+
+fn export(args: &Args) -> Result<(), CliError> {
+    let format = args.get("format").unwrap_or("text");
+    let rules = load_rules(args)?;
+    let gaddag = load_gaddag(args.dict(), &rules)?;
+    let grid = load_grid(args, &rules, &gaddag)?;
+    let content = match format {
+        "text" => grid_to_text(&grid),
+        "json" => format!("{:#}\n", grid_to_json(&grid)),
+        "svg" => grid_to_svg(&grid),
+        _ => {
+            return Err(CliError::Usage(format!(
+                "Unknown format: {} (expected text, json or svg)",
+                format
+            )))
+        }
+    };
+    match args.get("output") {
+        Some(output) => fs::write(output, content)
+            .map_err(|e| CliError::Failure(format!("Cannot write {}: {}", output, e))),
+        None => {
+            print!("{}", content);
+            Ok(())
+        }
+    }
+}
+
+fn rules(args: &Args) -> Result<(), CliError> {
+    // Sans argument, liste les règles fournies ; sinon affiche les règles demandées
+    let Some(name) = args.positional.first() else {
+        for name in RuleSet::presets() {
+            println!("{}", name);
+        }
+        return Ok(());
+    };
+    let rules = RuleSet::load(name)
+        .map_err(|e| CliError::Failure(format!("Cannot load rules {}: {}", name, e)))?;
+    match args.get("format").unwrap_or("toml") {
+        "toml" => print!("{}", rules.to_toml()),
+        "json" => println!("{}", rules.to_json()),
+        format => {
+            return Err(CliError::Usage(format!(
+                "Unknown format: {} (expected toml or json)",
+                format
+            )))
+        }
+    }
+    Ok(())
+}
+
+fn build_dict(args: &Args) -> Result<(), CliError> {
+    let output = args.require("output")?;
+    let input = args.dict();
+    let rules = load_rules(args)?;
+    let start = Instant::now();
+    let gaddag = Gaddag::read_words_from_file(input, &rules)
+        .map_err(|e| CliError::Failure(format!("Cannot read dictionary {}: {}", input, e)))?;
+    for rejected in gaddag.rejected() {
+        eprintln!("Rejected {}", rejected);
+    }
+    if !gaddag.rejected().is_empty() {
+        eprintln!("{} lines rejected", gaddag.rejected().len());
+    }
+    gaddag
+        .save(output)
+        .map_err(|e| CliError::Failure(format!("Cannot write {}: {}", output, e)))?;
+    println!(
+        "{} words from {} compiled to {} ({} nodes, {} edges, alphabet {}) in {:?}",
+        gaddag.words_nb(),
+        gaddag.source(),
+        output,
+        gaddag.nodes_nb(),
+        gaddag.edges_nb(),
+        gaddag.alphabet().join(" "),
+        start.elapsed()
+    );
+    Ok(())
+}
+
+fn run(raw: &[String]) -> Result<(), CliError> {
+    let Some((command, rest)) = raw.split_first() else {
+        return Err(CliError::Usage("Missing command".to_string()));
+    };
+    match command.as_str() {
+        "solve" => solve(&Args::parse(
+            rest,
+            &[
+                "dict",
+                "rules",
+                "board",
+                "moves",
+                "screenshot",
+                "rack",
+                "top",
+                "leaves",
+            ],
+        )?),
+        "endgame" => endgame(&Args::parse(
+            rest,
+            &[
+                "dict",
+                "rules",
+                "board",
+                "moves",
+                "screenshot",
+                "rack",
+                "opponent",
+                "time",
+                "depth",
+            ],
+        )?),
+        "preendgame" => preendgame(&Args::parse(
+            rest,
+            &[
+                "dict",
+                "rules",
+                "board",
+                "moves",
+                "screenshot",
+                "rack",
+                "leaves",
+                "candidates",
+                "time",
+                "depth",
+                "budget",
+                "spread",
+            ],
+        )?),
+        "sim" => sim(&Args::parse(
+            rest,
+            &[
+                "dict",
+                "rules",
+                "board",
+                "moves",
+                "screenshot",
+                "rack",
+                "leaves",
+                "candidates",
+                "iterations",
+                "plies",
+                "spread",
+                "seed",
+            ],
+        )?),
+        "unseen" => unseen(&Args::parse(
+            rest,
+            &["dict", "rules", "board", "moves", "screenshot", "rack"],
+        )?),
+        "simulate" => simulate(&Args::parse(
+            rest,
+            &["dict", "rules", "games", "seed", "leaves"],
+        )?),
+        "game" => game(&Args::parse(
+            rest,
+            &["dict", "rules", "players", "seed", "leaves"],
+        )?),
+        "check" => check(&Args::parse(rest, &["dict", "rules"])?),
+        "query" => query(&Args::parse(
+            rest,
+            &["dict", "rules", "rack", "min", "format"],
+        )?),
+        "export" => export(&Args::parse(
+            rest,
+            &[
+                "dict",
+                "rules",
+                "board",
+                "moves",
+                "screenshot",
+                "format",
+                "output",
+            ],
+        )?),
+        "build-dict" => build_dict(&Args::parse(rest, &["dict", "rules", "output"])?),
+        "rules" => rules(&Args::parse(rest, &["format"])?),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(CliError::Usage(format!("Unknown command: {}", command))),
+    }
+}
+
+fn main() -> ExitCode {
+    let raw: Vec<String> = std::env::args().skip(1).collect();
+    match run(&raw) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            match error {
+                CliError::Usage(_) => ExitCode::from(2),
+                CliError::Failure(_) => ExitCode::FAILURE,
+            }
+        }
+    }
+}
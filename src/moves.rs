@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::grid::{CoordError, Grid};
+use crate::rules::Tile;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Direction {
+    Horizontal,
+    Vertical,
+}
+
+impl Direction {
+    pub fn other(self) -> Direction {
+        match self {
+            Direction::Horizontal => Direction::Vertical,
+            Direction::Vertical => Direction::Horizontal,
+        }
+    }
+
+    pub fn offset(self, row: usize, col: usize, k: usize) -> (usize, usize) {
+        // Retourne la case située k cases plus loin dans cette direction
+        match self {
+            Direction::Horizontal => (row, col + k),
+            Direction::Vertical => (row + k, col),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PlacedTile {
+    pub row: usize,
+    pub col: usize,
+    // Lettre jouée, celle que représente le joker le cas échéant
+    pub letter: Tile,
+    pub blank: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Score {
+    pub main: usize,
+    pub cross: usize,
+    pub bingo: usize,
+}
+
+impl Score {
+    pub fn total(&self) -> usize {
+        self.main + self.cross + self.bingo
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Move {
+    pub row: usize,
+    pub col: usize,
+    pub direction: Direction,
+    // Mot complet, y compris les lettres déjà sur la grille ; les minuscules sont des jokers,
+    // et les lettres de plusieurs caractères comme CH sont découpées selon les règles
+    pub word: String,
+    // Jetons posés depuis le rack
+    pub placed: Vec<PlacedTile>,
+    pub score: Score,
+    pub leave: HashMap<Tile, usize>,
+}
+
+impl Move {
+    pub fn new(row: usize, col: usize, direction: Direction, word: &str) -> Self {
+        // Crée un coup dont les jetons posés et le score restent à déterminer sur une grille
+        Move {
+            row,
+            col,
+            direction,
+            word: word.to_string(),
+            placed: Vec::new(),
+            score: Score::default(),
+            leave: HashMap::new(),
+        }
+    }
+
+    pub fn parse(s: &str, size: usize) -> Result<Self, ParseMoveError> {
+        // Lit un coup en notation "H8 MOT" (horizontal) ou "8H MOT" (vertical), les
+        // coordonnées devant tenir dans une grille de size cases de côté
+        let mut parts = s.split_whitespace();
+        let (Some(reference), Some(word), None) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(ParseMoveError::Format(s.to_string()));
+        };
+        if !word.chars().all(|c| c.is_alphabetic() || c == '·') {
+            return Err(ParseMoveError::Word(word.to_string()));
+        }
+        let ((row, col), direction) = Grid::ref_to_pos(reference, size)?;
+        Ok(Move::new(row, col, direction, word))
+    }
+
+    pub fn coordinates(&self) -> String {
+        Grid::pos_to_ref((self.row, self.col), self.direction)
+    }
+
+    pub fn blanks(&self) -> impl Iterator<Item = &PlacedTile> + '_ {
+        // Jokers posés et la lettre qu'ils représentent
+        self.placed.iter().filter(|tile| tile.blank)
+    }
+}
+
+impl PartialEq for Move {
+    fn eq(&self, other: &Self) -> bool {
+        // Deux coups sont identiques s'ils posent le même mot au même endroit
+        (self.row, self.col, self.direction, &self.word)
+            == (other.row, other.col, other.direction, &other.word)
+    }
+}
+
+impl Eq for Move {}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.coordinates(), self.word)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseMoveError {
+    Format(String),
+    Word(String),
+    Coordinates(CoordError),
+}
+
+impl fmt::Display for ParseMoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseMoveError::Format(s) => {
+                write!(f, "expected \"<coordinates> <word>\", got \"{}\"", s)
+            }
+            ParseMoveError::Word(word) => write!(f, "invalid word: {}", word),
+            ParseMoveError::Coordinates(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for ParseMoveError {}
+
+impl From<CoordError> for ParseMoveError {
+    fn from(error: CoordError) -> Self {
+        ParseMoveError::Coordinates(error)
+    }
+}
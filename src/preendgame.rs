@@ -0,0 +1,499 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::endgame::{solve_endgame, EndgameConfig};
+use crate::gaddag::Gaddag;
+use crate::grid::Grid;
+use crate::leave::LeaveTable;
+use crate::moves::Move;
+use crate::rack::{difference, sum};
+use crate::rules::Tile;
+use crate::solver::generate_solutions;
+use crate::unseen::unseen_tiles;
+
+// Plus grand nombre de jetons dans le sac traité par l'analyse d'avant fin de partie
+pub const MAX_BAG_SIZE: usize = 6;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PreEndgameError {
+    // Nombre de jetons dans le sac hors de 1..=MAX_BAG_SIZE
+    BagSize(usize),
+    // La grille et le rack montrent plus de jetons que les règles n'en comptent
+    ImpossiblePosition,
+}
+
+impl fmt::Display for PreEndgameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreEndgameError::BagSize(size) => write!(
+                f,
+                "the bag holds {} tiles, not between 1 and {}",
+                size, MAX_BAG_SIZE
+            ),
+            PreEndgameError::ImpossiblePosition => {
+                write!(f, "the position shows more tiles than the game has")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PreEndgameError {}
+
+// Réglages de l'analyse d'avant fin de partie
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PreEndgameConfig {
+    // Nombre de coups candidats parmi les meilleurs, et autant parmi ceux qui vident le sac
+    pub candidates: usize,
+    // Réglages de chaque recherche de fin de partie, une par tirage
+    pub endgame: EndgameConfig,
+    // Durée totale de l'analyse, partagée entre les candidats : chacun reçoit une part égale
+    // du temps qui reste, au-delà de laquelle ses tirages restants ne sont plus évalués que
+    // par la valeur des jetons et des reliquats
+    pub time_limit: Duration,
+    // Avance au score avant le coup, pour les chances de victoire
+    pub spread: i64,
+}
+
+impl Default for PreEndgameConfig {
+    fn default() -> Self {
+        PreEndgameConfig {
+            candidates: 5,
+            endgame: EndgameConfig {
+                time_limit: Duration::from_secs(1),
+                max_depth: 6,
+            },
+            time_limit: Duration::from_secs(60),
+            spread: 0,
+        }
+    }
+}
+
+// Résultat d'un candidat sur l'ensemble des tirages possibles
+#[derive(Debug, Clone)]
+pub struct PreEndgameResult {
+    // Coup joué, None pour une passe
+    pub mv: Option<Move>,
+    // Nombre de tirages distincts examinés
+    pub draws: usize,
+    // Écart final moyen, pondéré par la probabilité de chaque tirage
+    pub spread: f64,
+    // Probabilité de gagner la partie, une égalité comptant pour moitié
+    pub win_rate: f64,
+    // Vrai si chaque tirage a été résolu par une fin de partie exacte, ce qui n'arrive qu'aux
+    // coups qui vident le sac ; sinon l'écart et les chances de victoire sont approchés
+    pub exact: bool,
+}
+
+// Candidats classés en deux groupes qui ne se comparent pas : ceux qui vident le sac, suivis
+// d'une fin de partie résolue pour chaque tirage, et ceux qui y laissent des jetons, passe
+// comprise, dont l'évaluation n'est qu'une estimation
+#[derive(Debug, Clone)]
+pub struct PreEndgameAnalysis {
+    pub emptying: Vec<PreEndgameResult>,
+    pub keeping: Vec<PreEndgameResult>,
+}
+
+fn binomial(n: usize, k: usize) -> f64 {
+    (0..k).fold(1.0, |acc, i| acc * (n - i) as f64 / (i + 1) as f64)
+}
+
+fn subsets(pool: &[(Tile, usize)], size: usize) -> Vec<(HashMap<Tile, usize>, f64)> {
+    // Sous-ensembles distincts de size jetons parmi pool, avec le nombre de façons de les
+    // tirer, proportionnel à leur probabilité
+    let Some((&(tile, count), rest)) = pool.split_first() else {
+        return if size == 0 {
+            vec![(HashMap::new(), 1.0)]
+        } else {
+            Vec::new()
+        };
+    };
+    let mut result = Vec::new();
+    for k in 0..=count.min(size) {
+        for (mut subset, weight) in subsets(rest, size - k) {
+            if k > 0 {
+                subset.insert(tile, k);
+            }
+            result.push((subset, weight * binomial(count, k)));
+        }
+    }
+    result
+}
+
+fn pool_list(tiles: &HashMap<Tile, usize>) -> Vec<(Tile, usize)> {
+    let mut pool: Vec<(Tile, usize)> = tiles
+        .iter()
+        .filter(|(_, &count)| count > 0)
+        .map(|(&tile, &count)| (tile, count))
+        .collect();
+    pool.sort_unstable();
+    pool
+}
+
+// Issue d'un tirage : l'écart final, le poids du tirage et si l'écart est exact
+struct Outcome {
+    spread: i64,
+    weight: f64,
+    exact: bool,
+}
+
+struct Analysis<'a> {
+    gaddag: &'a Gaddag,
+    leaves: &'a LeaveTable,
+    config: &'a PreEndgameConfig,
+    unseen: HashMap<Tile, usize>,
+    bag_size: usize,
+    deadline: Instant,
+}
+
+impl Analysis<'_> {
+    fn is_over(&self) -> bool {
+        Instant::now() >= self.deadline
+    }
+
+    fn endgame(
+        &self,
+        grid: &Grid,
+        rack: &HashMap<Tile, usize>,
+        opponent: &HashMap<Tile, usize>,
+    ) -> (i64, bool) {
+        // Écart de la fin de partie pour rack au trait, et s'il est exact ; une fois la durée
+        // totale écoulée, chacun est supposé garder ses jetons
+        let left = self.deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            let rules = grid.rules();
+            let value = rules.rack_value(opponent) as i64 - rules.rack_value(rack) as i64;
+            return (value, false);
+        }
+        let config = EndgameConfig {
+            time_limit: self.config.endgame.time_limit.min(left),
+            ..self.config.endgame
+        };
+        let result = solve_endgame(grid, rack, opponent, self.gaddag, &config);
+        (result.spread, result.exact)
+    }
+
+    fn emptying(&self, grid: &Grid, mv: &Move, rack: &HashMap<Tile, usize>) -> Vec<Outcome> {
+        // Le coup vide le sac : pour chaque contenu possible du sac, on le pioche entièrement
+        // et l'adversaire, qui a le reste, joue la fin de partie
+        let score = mv.score.total() as i64;
+        let mut after = grid.clone();
+        after.place(mv, self.gaddag);
+        subsets(&pool_list(&self.unseen), self.bag_size)
+            .into_iter()
+            .map(|(bag, weight)| {
+                let ours = sum(rack, &bag);
+                let theirs = difference(&self.unseen, &bag);
+                let (reply, exact) = self.endgame(&after, &theirs, &ours);
+                Outcome {
+                    spread: score - reply,
+                    weight,
+                    exact,
+                }
+            })
+            .collect()
+    }
+
+    fn keeping(
+        &self,
+        grid: &Grid,
+        mv: Option<&Move>,
+        leave: &HashMap<Tile, usize>,
+    ) -> Vec<Outcome> {
+        // Le sac garde des jetons : pour chaque pioche et chaque rack adverse possibles,
+        // l'adversaire choisit ce qui nous laisse le moins entre passer, son meilleur coup
+        // statique qui garde des jetons dans le sac, compté avec la valeur des reliquats, et
+        // son coup le mieux payé qui vide le sac, suivi de la fin de partie ; ne considérer
+        // que ces coups fait de l'écart une estimation. Après notre passe, repasser ramènerait
+        // la même position : l'adversaire ne le fait alors que s'il n'a aucun coup
+        let score = mv.map_or(0, |mv| mv.score.total() as i64);
+        let placed = mv.map_or(0, |mv| mv.placed.len());
+        let mut after = grid.clone();
+        if let Some(mv) = mv {
+            after.place(mv, self.gaddag);
+        }
+        let rack_size = grid.rules().rack_size;
+        let mut outcomes = Vec::new();
+        for (drawn, draw_weight) in subsets(&pool_list(&self.unseen), placed) {
+            let ours = sum(leave, &drawn);
+            let rest = difference(&self.unseen, &drawn);
+            for (theirs, rack_weight) in subsets(&pool_list(&rest), rack_size) {
+                let bag = difference(&rest, &theirs);
+                let bag_len: usize = bag.values().sum();
+                let static_value = |theirs: &HashMap<Tile, usize>| {
+                    (self.leaves.value(&ours) - self.leaves.value(theirs)).round() as i64
+                };
+                // Une fois le temps du candidat écoulé, on s'en tient à la valeur des reliquats
+                let mut moves = Vec::new();
+                if !self.is_over() {
+                    moves = generate_solutions(&after, &theirs, self.gaddag);
+                    self.leaves.rank(&mut moves);
+                }
+                let mut replies = Vec::new();
+                if mv.is_some() || moves.is_empty() {
+                    replies.push(score + static_value(&theirs));
+                }
+                if let Some(reply) = moves.iter().find(|reply| reply.placed.len() < bag_len) {
+                    replies.push(score - reply.score.total() as i64 + static_value(&reply.leave));
+                }
+                if let Some(reply) = moves
+                    .iter()
+                    .filter(|reply| reply.placed.len() >= bag_len)
+                    .max_by_key(|reply| reply.score.total())
+                {
+                    let mut last = after.clone();
+                    last.place(reply, self.gaddag);
+                    let theirs = sum(&reply.leave, &bag);
+                    let (end, _) = self.endgame(&last, &ours, &theirs);
+                    replies.push(score - reply.score.total() as i64 + end);
+                }
+                let spread = replies.into_iter().min().unwrap_or(score);
+                outcomes.push(Outcome {
+                    spread,
+                    weight: draw_weight * rack_weight,
+                    exact: false,
+                });
+            }
+        }
+        outcomes
+    }
+}
+
+pub fn solve_pre_endgame(
+    grid: &Grid,
+    rack: &HashMap<Tile, usize>,
+    gaddag: &Gaddag,
+    leaves: &LeaveTable,
+    config: &PreEndgameConfig,
+) -> Result<PreEndgameAnalysis, PreEndgameError> {
+    // Classe les meilleurs coups, ceux qui vident le sac et la passe selon leurs chances de
+    // victoire sur tous les tirages possibles des jetons invisibles, le sac en contenant de
+    // 1 à MAX_BAG_SIZE et l'adversaire ayant les autres ; les coups qui vident le sac et les
+    // autres sont classés séparément
+    let unseen = unseen_tiles(grid, rack);
+    if !unseen.is_possible() {
+        return Err(PreEndgameError::ImpossiblePosition);
+    }
+    let rack_size = grid.rules().rack_size;
+    let bag_size = unseen.len().saturating_sub(rack_size);
+    if bag_size == 0 || bag_size > MAX_BAG_SIZE {
+        return Err(PreEndgameError::BagSize(bag_size));
+    }
+    let mut moves = generate_solutions(grid, rack, gaddag);
+    leaves.rank(&mut moves);
+    let mut candidates: Vec<Option<Move>> = moves
+        .iter()
+        .take(config.candidates)
+        .cloned()
+        .map(Some)
+        .collect();
+    for mv in moves
+        .iter()
+        .filter(|mv| mv.placed.len() >= bag_size)
+        .take(config.candidates)
+    {
+        if !candidates
+            .iter()
+            .any(|candidate| candidate.as_ref() == Some(mv))
+        {
+            candidates.push(Some(mv.clone()));
+        }
+    }
+    candidates.push(None);
+    let mut analysis = Analysis {
+        gaddag,
+        leaves,
+        config,
+        unseen: unseen.tiles,
+        bag_size,
+        deadline: Instant::now(),
+    };
+    let end = Instant::now() + config.time_limit;
+    let candidates_nb = candidates.len();
+    let mut emptying = Vec::new();
+    let mut keeping = Vec::new();
+    for (k, mv) in candidates.into_iter().enumerate() {
+        // Chaque candidat a une part égale du temps restant, ce qu'un candidat n'utilise pas
+        // revenant aux suivants
+        let left = end.saturating_duration_since(Instant::now());
+        analysis.deadline = Instant::now() + left / (candidates_nb - k) as u32;
+        let empties = mv.as_ref().is_some_and(|mv| mv.placed.len() >= bag_size);
+        let outcomes = match &mv {
+            Some(mv) if empties => analysis.emptying(grid, mv, &mv.leave),
+            Some(mv) => analysis.keeping(grid, Some(mv), &mv.leave),
+            None => analysis.keeping(grid, None, rack),
+        };
+        let total: f64 = outcomes.iter().map(|outcome| outcome.weight).sum();
+        let total = if total > 0.0 { total } else { 1.0 };
+        let spread = outcomes
+            .iter()
+            .map(|outcome| outcome.spread as f64 * outcome.weight)
+            .sum::<f64>()
+            / total;
+        let wins = outcomes
+            .iter()
+            .map(|outcome| match (config.spread + outcome.spread).signum() {
+                1 => outcome.weight,
+                0 => outcome.weight / 2.0,
+                _ => 0.0,
+            })
+            .sum::<f64>();
+        let result = PreEndgameResult {
+            mv,
+            draws: outcomes.len(),
+            spread,
+            win_rate: wins / total,
+            exact: outcomes.iter().all(|outcome| outcome.exact),
+        };
+        if empties {
+            emptying.push(result);
+        } else {
+            keeping.push(result);
+        }
+    }
+    for results in [&mut emptying, &mut keeping] {
+        results.sort_by(|a, b| {
+            b.win_rate
+                .total_cmp(&a.win_rate)
+                .then_with(|| b.spread.total_cmp(&a.spread))
+        });
+    }
+    Ok(PreEndgameAnalysis { emptying, keeping })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::Direction;
+    use crate::rules::RuleSet;
+    use std::sync::Arc;
+
+    fn small_rules(counts: &[(&str, usize)]) -> Arc<RuleSet> {
+        // Règles françaises avec un chevalet de deux jetons et seulement les jetons donnés
+        let mut rules = (*RuleSet::french()).clone();
+        rules.rack_size = 2;
+        for (name, info) in rules.tiles.iter_mut() {
+            info.count = counts
+                .iter()
+                .find(|(tile, _)| tile == name)
+                .map_or(0, |&(_, count)| count);
+        }
+        Arc::new(rules)
+    }
+
+    fn position(counts: &[(&str, usize)]) -> (Arc<RuleSet>, Gaddag, Grid) {
+        // SAMA en H8 ; personne ne peut jouer de E ni de K
+        let rules = small_rules(counts);
+        let gaddag = Gaddag::from_words(&["SAMA", "SAMAS", "AS", "ZA", "ZAS"], "test", &rules);
+        let mut grid = Grid::with_rules(rules.clone());
+        grid.generate_grid();
+        grid.play(&Move::new(7, 7, Direction::Horizontal, "SAMA"), &gaddag)
+            .unwrap();
+        (rules, gaddag, grid)
+    }
+
+    const COUNTS: [(&str, usize); 6] = [("S", 2), ("A", 2), ("M", 1), ("Z", 1), ("E", 2), ("K", 1)];
+
+    #[test]
+    fn subsets_are_weighted_by_the_ways_to_draw_them() {
+        assert_eq!(binomial(5, 2), 10.0);
+        assert_eq!(binomial(4, 0), 1.0);
+        assert_eq!(binomial(2, 3), 0.0);
+        let pool = [(0, 2), (1, 1)];
+        let mut pairs: Vec<(Vec<Tile>, f64)> = subsets(&pool, 2)
+            .into_iter()
+            .map(|(subset, weight)| (crate::rack::rack_tiles(&subset), weight))
+            .collect();
+        pairs.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(pairs, [(vec![0, 0], 1.0), (vec![0, 1], 2.0)]);
+        // Les poids comptent tous les tirages : C(6, 3) pour trois jetons parmi six
+        let pool = [(0, 3), (1, 2), (2, 1)];
+        let total: f64 = subsets(&pool, 3).iter().map(|(_, weight)| weight).sum();
+        assert_eq!(total, 20.0);
+        assert!(subsets(&[(0, 1)], 2).is_empty());
+    }
+
+    #[test]
+    fn emptying_the_bag_solves_every_draw() {
+        // Le sac ne contient qu'un jeton parmi E, E et K ; après ZAS, personne ne peut plus
+        // jouer et chacun perd ses jetons : 23 + 11 - 1 si l'on pioche un E, deux fois sur
+        // trois, et 23 + 2 - 10 si l'on pioche le K
+        let (rules, gaddag, grid) = position(&COUNTS);
+        let rack = rules.parse_rack("SZ").unwrap();
+        let analysis = solve_pre_endgame(
+            &grid,
+            &rack,
+            &gaddag,
+            &LeaveTable::new(),
+            &PreEndgameConfig::default(),
+        )
+        .unwrap();
+        assert!(!analysis.emptying.is_empty());
+        assert!(analysis.emptying.iter().all(|result| !result
+            .mv
+            .as_ref()
+            .unwrap()
+            .placed
+            .is_empty()
+            && result.exact));
+        let zas = analysis
+            .emptying
+            .iter()
+            .find(|result| result.mv.as_ref().unwrap().word == "ZAS")
+            .unwrap();
+        assert_eq!(zas.draws, 2);
+        assert_eq!(zas.spread, (2.0 * 33.0 + 15.0) / 3.0);
+        assert_eq!(zas.win_rate, 1.0);
+        // Sans coup possible, l'adversaire repasse après notre passe
+        assert_eq!(analysis.keeping.len(), 1);
+        let pass = &analysis.keeping[0];
+        assert!(pass.mv.is_none());
+        assert_eq!(pass.draws, 2);
+        assert_eq!(pass.spread, 0.0);
+        assert_eq!(pass.win_rate, 0.5);
+        assert!(!pass.exact);
+    }
+
+    #[test]
+    fn a_spent_budget_still_values_every_candidate() {
+        let (rules, gaddag, grid) = position(&COUNTS);
+        let rack = rules.parse_rack("SZ").unwrap();
+        let leaves = LeaveTable::new();
+        let full = solve_pre_endgame(&grid, &rack, &gaddag, &leaves, &PreEndgameConfig::default())
+            .unwrap();
+        let config = PreEndgameConfig {
+            time_limit: Duration::ZERO,
+            ..PreEndgameConfig::default()
+        };
+        let spent = solve_pre_endgame(&grid, &rack, &gaddag, &leaves, &config).unwrap();
+        assert_eq!(spent.emptying.len(), full.emptying.len());
+        assert_eq!(spent.keeping.len(), full.keeping.len());
+        assert!(spent.emptying.iter().all(|result| !result.exact));
+        assert!(spent.emptying.iter().all(|result| result.draws == 2));
+    }
+
+    #[test]
+    fn bag_size_and_impossible_positions_are_refused() {
+        let leaves = LeaveTable::new();
+        let config = PreEndgameConfig::default();
+        // Deux jetons invisibles remplissent le chevalet adverse : le sac est vide
+        let (rules, gaddag, grid) = position(&[("S", 2), ("A", 2), ("M", 1), ("Z", 1), ("E", 2)]);
+        let rack = rules.parse_rack("SZ").unwrap();
+        assert_eq!(
+            solve_pre_endgame(&grid, &rack, &gaddag, &leaves, &config).unwrap_err(),
+            PreEndgameError::BagSize(0)
+        );
+        let (rules, gaddag, grid) = position(&[("S", 2), ("A", 2), ("M", 1), ("Z", 1), ("E", 9)]);
+        let rack = rules.parse_rack("SZ").unwrap();
+        assert_eq!(
+            solve_pre_endgame(&grid, &rack, &gaddag, &leaves, &config).unwrap_err(),
+            PreEndgameError::BagSize(7)
+        );
+        let (rules, gaddag, grid) = position(&COUNTS);
+        let rack = rules.parse_rack("ZZ").unwrap();
+        assert_eq!(
+            solve_pre_endgame(&grid, &rack, &gaddag, &leaves, &config).unwrap_err(),
+            PreEndgameError::ImpossiblePosition
+        );
+    }
+}
@@ -0,0 +1,429 @@
+use std::collections::HashMap;
+
+use crate::gaddag::{Gaddag, NodeId, SEPARATOR};
+use crate::rules::{Letter, RuleSet, Tile, BLANK};
+
+// Élément d'un motif de recherche comme "?A*S"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatternItem {
+    // Lettre imposée, qui ne vient pas du rack
+    Letter(Tile),
+    // Une lettre quelconque, notée '?'
+    Any,
+    // Un nombre quelconque de lettres, éventuellement aucune, noté '*'
+    Many,
+}
+
+pub fn parse_pattern(rules: &RuleSet, text: &str) -> Result<Vec<PatternItem>, char> {
+    // Découpe un motif en éléments, les lettres étant lues comme dans un mot du dictionnaire
+    let text = rules.normalize(text);
+    let mut pattern = Vec::new();
+    let mut rest = text.as_str();
+    while let Some(c) = rest.chars().next() {
+        let (item, len) = match c {
+            '?' => (PatternItem::Any, 1),
+            '*' => (PatternItem::Many, 1),
+            _ => match rules.read_letter(rest) {
+                Some((letter, len)) => (PatternItem::Letter(letter.tile), len),
+                None => return Err(c),
+            },
+        };
+        pattern.push(item);
+        rest = &rest[len..];
+    }
+    Ok(pattern)
+}
+
+// Parcours des mots du gaddag depuis leur première lettre, en suivant un motif
+struct Search<'a> {
+    gaddag: &'a Gaddag,
+    pattern: &'a [PatternItem],
+    // Lettres disponibles pour les éléments libres du motif, sans limite si absent
+    rack: Option<HashMap<Tile, usize>>,
+    prefix: Vec<Letter>,
+    results: Vec<Vec<Letter>>,
+}
+
+impl Search<'_> {
+    fn next_node(&self, node: NodeId, tile: Tile) -> Option<NodeId> {
+        // Avance d'une lettre dans le mot : la première est suivie du séparateur
+        let child = self.gaddag.child(node, tile)?;
+        if self.prefix.is_empty() {
+            self.gaddag.child(child, SEPARATOR)
+        } else {
+            Some(child)
+        }
+    }
+
+    fn take(&mut self, tile: Tile) -> Option<Letter> {
+        // Retire tile du rack, en utilisant un joker seulement s'il n'en reste plus ; chaque mot
+        // n'est ainsi trouvé qu'une fois, avec les jokers sur les dernières occurrences
+        let Some(rack) = &mut self.rack else {
+            return Some(Letter { tile, blank: false });
+        };
+        let letter = if rack.get(&tile).is_some_and(|&count| count > 0) {
+            Letter { tile, blank: false }
+        } else if rack.get(&BLANK).is_some_and(|&count| count > 0) {
+            Letter { tile, blank: true }
+        } else {
+            return None;
+        };
+        *rack
+            .get_mut(&if letter.blank { BLANK } else { tile })
+            .unwrap() -= 1;
+        Some(letter)
+    }
+
+    fn give_back(&mut self, letter: Letter) {
+        if let Some(rack) = &mut self.rack {
+            *rack
+                .entry(if letter.blank { BLANK } else { letter.tile })
+                .or_insert(0) += 1;
+        }
+    }
+
+    fn extend_free(&mut self, node: NodeId, index: usize) {
+        // Essaie chaque lettre possible après le préfixe pour un élément libre du motif, en
+        // restant sur l'élément s'il s'agit de '*'
+        let next_index = match self.pattern[index] {
+            PatternItem::Many => index,
+            _ => index + 1,
+        };
+        let children: Vec<(Tile, NodeId)> = self.gaddag.children(node).collect();
+        for (tile, _) in children {
+            if tile == SEPARATOR {
+                continue;
+            }
+            let Some(next) = self.next_node(node, tile) else {
+                continue;
+            };
+            let Some(letter) = self.take(tile) else {
+                continue;
+            };
+            self.prefix.push(letter);
+            self.explore(next, next_index);
+            self.prefix.pop();
+            self.give_back(letter);
+        }
+    }
+
+    fn explore(&mut self, node: NodeId, index: usize) {
+        // Complète le préfixe à partir de l'élément index du motif
+        if index == self.pattern.len() {
+            if !self.prefix.is_empty() && self.gaddag.is_word(node) {
+                self.results.push(self.prefix.clone());
+            }
+            return;
+        }
+        match self.pattern[index] {
+            PatternItem::Letter(tile) => {
+                if let Some(next) = self.next_node(node, tile) {
+                    self.prefix.push(Letter { tile, blank: false });
+                    self.explore(next, index + 1);
+                    self.prefix.pop();
+                }
+            }
+            PatternItem::Any => self.extend_free(node, index),
+            PatternItem::Many => {
+                self.explore(node, index + 1);
+                self.extend_free(node, index);
+            }
+        }
+    }
+}
+
+fn search(
+    gaddag: &Gaddag,
+    pattern: &[PatternItem],
+    rack: Option<&HashMap<Tile, usize>>,
+) -> Vec<Vec<Letter>> {
+    // Liste les mots correspondant au motif, les plus longs d'abord puis dans l'ordre de
+    // l'alphabet, chaque mot n'apparaissant qu'une fois
+    let mut search = Search {
+        gaddag,
+        pattern,
+        rack: rack.cloned(),
+        prefix: Vec::new(),
+        results: Vec::new(),
+    };
+    search.explore(gaddag.root(), 0);
+    let mut results = search.results;
+    let tiles = |word: &[Letter]| word.iter().map(|letter| letter.tile).collect::<Vec<Tile>>();
+    results.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| tiles(a).cmp(&tiles(b))));
+    results.dedup_by(|a, b| tiles(a) == tiles(b));
+    results
+}
+
+pub fn anagrams(gaddag: &Gaddag, rack: &HashMap<Tile, usize>) -> Vec<Vec<Letter>> {
+    // Mots utilisant toutes les lettres du rack, les jokers étant en minuscules
+    let pattern = vec![PatternItem::Any; rack.values().sum()];
+    search(gaddag, &pattern, Some(rack))
+}
+
+pub fn sub_anagrams(
+    gaddag: &Gaddag,
+    rack: &HashMap<Tile, usize>,
+    min_length: usize,
+) -> Vec<Vec<Letter>> {
+    // Mots d'au moins min_length lettres utilisant une partie des lettres du rack
+    let mut words = search(gaddag, &[PatternItem::Many], Some(rack));
+    words.retain(|word| word.len() >= min_length);
+    words
+}
+
+pub fn pattern_matches(
+    gaddag: &Gaddag,
+    pattern: &[PatternItem],
+    rack: Option<&HashMap<Tile, usize>>,
+) -> Vec<Vec<Letter>> {
+    // Mots correspondant au motif, les éléments libres étant pris dans le rack s'il est donné
+    search(gaddag, pattern, rack)
+}
+
+pub fn front_hooks(gaddag: &Gaddag, word: &[Tile]) -> Vec<Tile> {
+    // Lettres qui peuvent précéder word : le mot allongé se lit à l'envers depuis sa
+    // dernière lettre jusqu'à la lettre ajoutée, suivie du séparateur
+    let reversed: Vec<Tile> = word.iter().rev().copied().collect();
+    let Some(node) = gaddag.follow_path(gaddag.root(), &reversed) else {
+        return Vec::new();
+    };
+    gaddag
+        .children(node)
+        .filter(|&(tile, child)| {
+            tile != SEPARATOR
+                && gaddag
+                    .child(child, SEPARATOR)
+                    .is_some_and(|end| gaddag.is_word(end))
+        })
+        .map(|(tile, _)| tile)
+        .collect()
+}
+
+pub fn back_hooks(gaddag: &Gaddag, word: &[Tile]) -> Vec<Tile> {
+    // Lettres qui peuvent suivre word, lu depuis sa première lettre
+    let Some((&first, rest)) = word.split_first() else {
+        return Vec::new();
+    };
+    let node = gaddag
+        .child(gaddag.root(), first)
+        .and_then(|node| gaddag.child(node, SEPARATOR))
+        .and_then(|node| gaddag.follow_path(node, rest));
+    let Some(node) = node else {
+        return Vec::new();
+    };
+    gaddag
+        .children(node)
+        .filter(|&(tile, child)| tile != SEPARATOR && gaddag.is_word(child))
+        .map(|(tile, _)| tile)
+        .collect()
+}
+
+pub fn inner_hooks(gaddag: &Gaddag, word: &[Tile]) -> Vec<(usize, Tile)> {
+    // Lettres qui peuvent être insérées dans word, avec la position d'insertion ; le mot
+    // allongé est lu depuis la lettre qui précède l'insertion, à l'envers puis à l'endroit,
+    // et un même mot obtenu par deux insertions n'est donné qu'une fois
+    let mut hooks: Vec<(usize, Tile)> = Vec::new();
+    let mut found: Vec<Vec<Tile>> = Vec::new();
+    for position in 1..word.len() {
+        let mut path: Vec<Tile> = word[..position].iter().rev().copied().collect();
+        path.push(SEPARATOR);
+        let Some(node) = gaddag.follow_path(gaddag.root(), &path) else {
+            continue;
+        };
+        for (tile, child) in gaddag.children(node) {
+            if tile == SEPARATOR
+                || !gaddag
+                    .follow_path(child, &word[position..])
+                    .is_some_and(|end| gaddag.is_word(end))
+            {
+                continue;
+            }
+            let extended = insert(word, position, tile);
+            if !found.contains(&extended) {
+                found.push(extended);
+                hooks.push((position, tile));
+            }
+        }
+    }
+    hooks
+}
+
+fn insert(word: &[Tile], position: usize, tile: Tile) -> Vec<Tile> {
+    let mut extended = word.to_vec();
+    extended.insert(position, tile);
+    extended
+}
+
+// Rallonges d'un mot : lettres ajoutées devant, derrière ou insérées à une position
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Hooks {
+    pub front: Vec<Tile>,
+    pub back: Vec<Tile>,
+    pub inner: Vec<(usize, Tile)>,
+}
+
+pub fn hooks(gaddag: &Gaddag, word: &[Tile]) -> Hooks {
+    Hooks {
+        front: front_hooks(gaddag, word),
+        back: back_hooks(gaddag, word),
+        inner: inner_hooks(gaddag, word),
+    }
+}
+
+pub fn study_card(rules: &RuleSet, word: &[Tile], hooks: &Hooks) -> String {
+    // Fiche de révision : le mot au recto, puis chaque rallonge avec le mot obtenu, la lettre
+    // ajoutée étant entre parenthèses
+    let marked = |position: usize, tile: Tile| {
+        format!(
+            "{}({}){}",
+            rules.tiles_text(&word[..position]),
+            rules.name(tile),
+            rules.tiles_text(&word[position..])
+        )
+    };
+    let line = |label: &str, words: Vec<String>| {
+        let words = if words.is_empty() {
+            "-".to_string()
+        } else {
+            words.join(" ")
+        };
+        format!("  {:<6}{}\n", label, words)
+    };
+    let mut card = format!("{}\n", rules.tiles_text(word));
+    card += &line(
+        "front",
+        hooks.front.iter().map(|&tile| marked(0, tile)).collect(),
+    );
+    card += &line(
+        "back",
+        hooks
+            .back
+            .iter()
+            .map(|&tile| marked(word.len(), tile))
+            .collect(),
+    );
+    card += &line(
+        "inner",
+        hooks
+            .inner
+            .iter()
+            .map(|&(position, tile)| marked(position, tile))
+            .collect(),
+    );
+    card
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORDS: [&str; 13] = [
+        "AS", "SA", "ARS", "RAS", "TAS", "MAS", "SAMA", "AMAS", "RASE", "TASSE", "TASE", "MAIS",
+        "MARS",
+    ];
+
+    fn texts(rules: &RuleSet, words: &[Vec<Letter>]) -> Vec<String> {
+        words.iter().map(|word| rules.word_text(word)).collect()
+    }
+
+    #[test]
+    fn anagrams_use_blanks_only_when_needed() {
+        let rules = RuleSet::french();
+        let gaddag = Gaddag::from_words(&WORDS, "test", &rules);
+        let rack = |text: &str| rules.parse_rack(text).unwrap();
+        assert_eq!(
+            texts(&rules, &anagrams(&gaddag, &rack("SRA"))),
+            ["ARS", "RAS"]
+        );
+        assert_eq!(
+            texts(&rules, &anagrams(&gaddag, &rack("AS?"))),
+            ["ArS", "mAS", "rAS", "tAS"]
+        );
+        assert_eq!(
+            texts(&rules, &anagrams(&gaddag, &rack("AAS?"))),
+            ["AmAS", "SAmA"]
+        );
+        assert!(anagrams(&gaddag, &rack("ZZ")).is_empty());
+    }
+
+    #[test]
+    fn sub_anagrams_respect_the_minimum_length() {
+        let rules = RuleSet::french();
+        let gaddag = Gaddag::from_words(&WORDS, "test", &rules);
+        let rack = rules.parse_rack("ARS").unwrap();
+        assert_eq!(
+            texts(&rules, &sub_anagrams(&gaddag, &rack, 2)),
+            ["ARS", "RAS", "AS", "SA"]
+        );
+        assert_eq!(
+            texts(&rules, &sub_anagrams(&gaddag, &rack, 3)),
+            ["ARS", "RAS"]
+        );
+    }
+
+    #[test]
+    fn patterns_with_and_without_a_rack() {
+        let rules = RuleSet::french();
+        let gaddag = Gaddag::from_words(&WORDS, "test", &rules);
+        let pattern = |text: &str| parse_pattern(&rules, text).unwrap();
+        assert_eq!(
+            texts(&rules, &pattern_matches(&gaddag, &pattern("?as"), None)),
+            ["MAS", "RAS", "TAS"]
+        );
+        assert_eq!(
+            texts(&rules, &pattern_matches(&gaddag, &pattern("*AS"), None)),
+            ["AMAS", "MAS", "RAS", "TAS", "AS"]
+        );
+        let rack = rules.parse_rack("T").unwrap();
+        assert_eq!(
+            texts(
+                &rules,
+                &pattern_matches(&gaddag, &pattern("?AS"), Some(&rack))
+            ),
+            ["TAS"]
+        );
+        assert_eq!(parse_pattern(&rules, "A-S"), Err('-'));
+    }
+
+    #[test]
+    fn front_and_back_hooks() {
+        let rules = RuleSet::french();
+        let gaddag = Gaddag::from_words(&WORDS, "test", &rules);
+        let tiles = |text: &str| rules.parse_tiles(text).unwrap();
+        assert_eq!(rules.tiles_text(&front_hooks(&gaddag, &tiles("AS"))), "MRT");
+        assert_eq!(rules.tiles_text(&back_hooks(&gaddag, &tiles("RAS"))), "E");
+        assert!(back_hooks(&gaddag, &tiles("AS")).is_empty());
+        assert!(front_hooks(&gaddag, &tiles("SAMA")).is_empty());
+        assert!(front_hooks(&gaddag, &tiles("XYZ")).is_empty());
+        assert!(back_hooks(&gaddag, &[]).is_empty());
+    }
+
+    #[test]
+    fn inner_hooks_give_each_longer_word_once() {
+        let rules = RuleSet::french();
+        let gaddag = Gaddag::from_words(&WORDS, "test", &rules);
+        let tiles = |text: &str| rules.parse_tiles(text).unwrap();
+        let tile = |name: &str| rules.tile(name).unwrap();
+        assert_eq!(
+            inner_hooks(&gaddag, &tiles("MAS")),
+            [(2, tile("I")), (2, tile("R"))]
+        );
+        assert_eq!(inner_hooks(&gaddag, &tiles("TASE")), [(2, tile("S"))]);
+        assert!(inner_hooks(&gaddag, &tiles("SAMA")).is_empty());
+    }
+
+    #[test]
+    fn study_card_lists_every_hook() {
+        let rules = RuleSet::french();
+        let gaddag = Gaddag::from_words(&WORDS, "test", &rules);
+        let word = rules.parse_tiles("AS").unwrap();
+        let hooks = hooks(&gaddag, &word);
+        assert_eq!(rules.tiles_text(&hooks.front), "MRT");
+        assert!(hooks.back.is_empty());
+        assert_eq!(hooks.inner, [(1, rules.tile("R").unwrap())]);
+        assert_eq!(
+            study_card(&rules, &word, &hooks),
+            "AS\n  front (M)AS (R)AS (T)AS\n  back  -\n  inner A(R)S\n"
+        );
+    }
+}
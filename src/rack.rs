@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use crate::rules::Tile;
+
+// Opérations sur les chevalets, vus comme le nombre d'exemplaires de chaque jeton, BLANK
+// comptant les jokers ; un jeton absent n'a jamais d'entrée à zéro
+
+pub fn add_tiles(rack: &mut HashMap<Tile, usize>, tiles: &[Tile]) {
+    // Ajoute tiles au chevalet
+    for &tile in tiles {
+        *rack.entry(tile).or_insert(0) += 1;
+    }
+}
+
+pub fn remove_tiles(rack: &mut HashMap<Tile, usize>, tiles: &[Tile]) {
+    // Retire tiles du chevalet, ceux qui n'y sont pas étant ignorés
+    for tile in tiles {
+        if let Some(count) = rack.get_mut(tile) {
+            *count -= 1;
+            if *count == 0 {
+                rack.remove(tile);
+            }
+        }
+    }
+}
+
+pub fn missing_tiles(rack: &HashMap<Tile, usize>, tiles: &[Tile]) -> Vec<Tile> {
+    // Jetons de tiles qui ne sont pas sur le chevalet, en tenant compte des doublons
+    let mut left = rack.clone();
+    let mut missing = Vec::new();
+    for &tile in tiles {
+        match left.get_mut(&tile) {
+            Some(count) if *count > 0 => *count -= 1,
+            _ => missing.push(tile),
+        }
+    }
+    missing
+}
+
+pub fn sum(rack: &HashMap<Tile, usize>, other: &HashMap<Tile, usize>) -> HashMap<Tile, usize> {
+    // Chevalet réunissant les jetons des deux
+    let mut sum = rack.clone();
+    for (&tile, &count) in other {
+        *sum.entry(tile).or_insert(0) += count;
+    }
+    sum
+}
+
+pub fn difference(
+    rack: &HashMap<Tile, usize>,
+    other: &HashMap<Tile, usize>,
+) -> HashMap<Tile, usize> {
+    // Jetons du chevalet qui restent une fois ceux de other retirés
+    let mut rest = rack.clone();
+    for (&tile, &count) in other {
+        if let Some(left) = rest.get_mut(&tile) {
+            *left = left.saturating_sub(count);
+        }
+    }
+    rest.retain(|_, count| *count > 0);
+    rest
+}
+
+pub fn rack_tiles(rack: &HashMap<Tile, usize>) -> Vec<Tile> {
+    // Jetons du chevalet dans l'ordre de l'alphabet, le joker en dernier
+    let mut tiles: Vec<Tile> = rack
+        .iter()
+        .flat_map(|(&tile, &count)| std::iter::repeat_n(tile, count))
+        .collect();
+    tiles.sort_unstable();
+    tiles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::BLANK;
+
+    #[test]
+    fn rack_arithmetic_keeps_counts_and_drops_empty_tiles() {
+        let mut rack = HashMap::new();
+        add_tiles(&mut rack, &[0, 0, 4, BLANK]);
+        assert_eq!(rack_tiles(&rack), vec![0, 0, 4, BLANK]);
+        assert_eq!(missing_tiles(&rack, &[0, 0, 0, 4, 7]), vec![0, 7]);
+        let other = HashMap::from([(0, 1), (4, 1), (7, 2)]);
+        assert_eq!(
+            rack_tiles(&sum(&rack, &other)),
+            vec![0, 0, 0, 4, 4, 7, 7, BLANK]
+        );
+        assert_eq!(
+            difference(&rack, &other),
+            HashMap::from([(0, 1), (BLANK, 1)])
+        );
+        remove_tiles(&mut rack, &[4, BLANK, 9]);
+        assert_eq!(rack, HashMap::from([(0, 2)]));
+    }
+}
@@ -0,0 +1,506 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+use serde::{Deserialize, Serialize};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::constants::MAX_GRID_SIZE;
+
+// Index d'un jeton dans l'alphabet des règles
+pub type Tile = u8;
+
+// Joker du chevalet, tant qu'on ne lui a pas choisi de lettre
+pub const BLANK: Tile = Tile::MAX;
+
+// Nombre maximal de lettres dans un alphabet, les dernières valeurs de Tile étant réservées
+const MAX_LETTERS: usize = 250;
+
+// Jeton posé : la lettre de l'alphabet qu'il porte, et s'il s'agit d'un joker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Letter {
+    pub tile: Tile,
+    pub blank: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum Square {
+    Blank,
+    LCD,
+    LCT,
+    LCQ,
+    MCD,
+    MCT,
+    MCQ,
+    Letter(Letter),
+}
+
+pub fn square_marker(square: Square) -> char {
+    // Marqueur d'une case vide dans l'export texte : '.' pour une case simple,
+    // et pour les cases bonus '=' MCT, '-' MCD, '~' MCQ, '"' LCT, '\'' LCD, '^' LCQ
+    match square {
+        Square::Letter(_) | Square::Blank => '.',
+        Square::LCD => '\'',
+        Square::LCT => '"',
+        Square::LCQ => '^',
+        Square::MCD => '-',
+        Square::MCT => '=',
+        Square::MCQ => '~',
+    }
+}
+
+pub fn marker_square(c: char) -> Option<Square> {
+    // Case vide correspondant à un marqueur de l'export texte
+    match c {
+        '.' => Some(Square::Blank),
+        '\'' => Some(Square::LCD),
+        '"' => Some(Square::LCT),
+        '^' => Some(Square::LCQ),
+        '-' => Some(Square::MCD),
+        '=' => Some(Square::MCT),
+        '~' => Some(Square::MCQ),
+        _ => None,
+    }
+}
+
+// Règles fournies avec le solveur, sous la forme des fichiers TOML du dossier rules
+const PRESETS: &[(&str, &str)] = &[
+    ("french", include_str!("../rules/french.toml")),
+    ("english", include_str!("../rules/english.toml")),
+    ("spanish", include_str!("../rules/spanish.toml")),
+    ("german", include_str!("../rules/german.toml")),
+    ("italian", include_str!("../rules/italian.toml")),
+    ("catalan", include_str!("../rules/catalan.toml")),
+    ("super", include_str!("../rules/super.toml")),
+];
+
+// Autres noms acceptés pour les règles fournies
+const ALIASES: &[(&str, &str)] = &[
+    ("ods", "french"),
+    ("twl", "english"),
+    ("collins", "english"),
+    ("superscrabble", "super"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RulesError {
+    Io(String),
+    Parse(String),
+    Invalid(String),
+}
+
+impl fmt::Display for RulesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RulesError::Io(error) => write!(f, "{}", error),
+            RulesError::Parse(error) => write!(f, "cannot parse the rules: {}", error),
+            RulesError::Invalid(error) => write!(f, "invalid rules: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for RulesError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TileInfo {
+    pub value: usize,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleSet {
+    pub name: String,
+    // Lettres jouables, dans l'ordre de l'alphabet ; une lettre peut tenir en plusieurs
+    // caractères, comme CH en espagnol, et le joker '?' n'en fait pas partie
+    pub alphabet: Vec<String>,
+    // Lettres comptées comme voyelles ou comme consonnes pour les tirages, une lettre
+    // pouvant être les deux comme le Y en français
+    pub vowels: Vec<String>,
+    pub consonants: Vec<String>,
+    pub rack_size: usize,
+    // Cases bonus avec les marqueurs de l'export texte, une ligne par rangée
+    pub board: String,
+    // Case (rangée, colonne) que doit couvrir le premier coup
+    pub start: (usize, usize),
+    // Prime selon le nombre de jetons posés
+    pub bingo: BTreeMap<usize, usize>,
+    // Valeur et nombre de chaque jeton, joker '?' compris
+    pub tiles: BTreeMap<String, TileInfo>,
+    // Remplacements appliqués aux mots des dictionnaires avant de les découper en lettres,
+    // comme Œ en OE
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub replacements: BTreeMap<String, String>,
+}
+
+impl RuleSet {
+    pub fn presets() -> impl Iterator<Item = &'static str> {
+        PRESETS.iter().map(|(name, _)| *name)
+    }
+
+    pub fn preset(name: &str) -> Option<RuleSet> {
+        // Règles fournies, par nom ou par alias, sans tenir compte de la casse
+        let name = name.to_lowercase();
+        let name = ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == name)
+            .map_or(name.as_str(), |(_, preset)| preset);
+        PRESETS
+            .iter()
+            .find(|(preset, _)| *preset == name)
+            .map(|(_, text)| RuleSet::from_toml(text).expect("the bundled rules are valid"))
+    }
+
+    pub fn french() -> Arc<RuleSet> {
+        // Règles par défaut, lues une seule fois et partagées
+        static FRENCH: OnceLock<Arc<RuleSet>> = OnceLock::new();
+        FRENCH
+            .get_or_init(|| {
+                Arc::new(RuleSet::preset("french").expect("the French rules are bundled"))
+            })
+            .clone()
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, RulesError> {
+        let rules: RuleSet = toml::from_str(text).map_err(|e| RulesError::Parse(e.to_string()))?;
+        rules.validate()?;
+        Ok(rules)
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, RulesError> {
+        let rules: RuleSet =
+            serde_json::from_str(text).map_err(|e| RulesError::Parse(e.to_string()))?;
+        rules.validate()?;
+        Ok(rules)
+    }
+
+    pub fn from_file(filename: &str) -> Result<Self, RulesError> {
+        // Lit un fichier JSON s'il en a l'extension, TOML sinon
+        let text = fs::read_to_string(filename)
+            .map_err(|e| RulesError::Io(format!("cannot read {}: {}", filename, e)))?;
+        let is_json = Path::new(filename)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        if is_json {
+            RuleSet::from_json(&text)
+        } else {
+            RuleSet::from_toml(&text)
+        }
+    }
+
+    pub fn load(name: &str) -> Result<Self, RulesError> {
+        // Règles fournies si name en désigne une, fichier sinon
+        match RuleSet::preset(name) {
+            Some(rules) => Ok(rules),
+            None => RuleSet::from_file(name),
+        }
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("rules can always be written as TOML")
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("rules can always be written as JSON")
+    }
+
+    fn board_rows(&self) -> Vec<Vec<char>> {
+        self.board
+            .lines()
+            .map(|line| line.chars().filter(|c| !c.is_whitespace()).collect())
+            .filter(|row: &Vec<char>| !row.is_empty())
+            .collect()
+    }
+
+    pub fn validate(&self) -> Result<(), RulesError> {
+        // Vérifie la cohérence des règles avant de s'en servir
+        let invalid = |message: String| Err(RulesError::Invalid(message));
+        if self.alphabet.is_empty() || self.alphabet.len() > MAX_LETTERS {
+            return invalid(format!(
+                "the alphabet must have 1 to {} letters",
+                MAX_LETTERS
+            ));
+        }
+        for (k, name) in self.alphabet.iter().enumerate() {
+            // Une lettre est écrite en majuscules et commence par un caractère alphabétique,
+            // suivi d'autres caractères alphabétiques ou du point médian de L·L
+            let valid = name.starts_with(char::is_alphabetic)
+                && name.chars().all(|c| c.is_alphabetic() || c == '·')
+                && *name == name.to_uppercase();
+            if !valid || self.alphabet[..k].contains(name) {
+                return invalid(format!(
+                    "invalid or repeated letter '{}' in the alphabet",
+                    name
+                ));
+            }
+            if !self.tiles.contains_key(name) {
+                return invalid(format!("no value or count for the letter {}", name));
+            }
+        }
+        for name in self
+            .tiles
+            .keys()
+            .chain(&self.vowels)
+            .chain(&self.consonants)
+        {
+            if name != "?" && !self.alphabet.contains(name) {
+                return invalid(format!("the letter {} is not in the alphabet", name));
+            }
+        }
+        if self.replacements.keys().any(String::is_empty) {
+            return invalid("a replacement must apply to at least one character".to_string());
+        }
+        if self.rack_size == 0 {
+            return invalid("the rack size must be positive".to_string());
+        }
+        // La grille est carrée, ses rangées étant nommées de A à Z au plus
+        let rows = self.board_rows();
+        let size = rows.len();
+        if !(2..=MAX_GRID_SIZE).contains(&size) {
+            return invalid(format!(
+                "the board has {} rows, expected 2 to {}",
+                size, MAX_GRID_SIZE
+            ));
+        }
+        if let Some(k) = rows.iter().position(|row| row.len() != size) {
+            return invalid(format!(
+                "row {} of the board has {} squares, expected {}",
+                k + 1,
+                rows[k].len(),
+                size
+            ));
+        }
+        if let Some(c) = rows.iter().flatten().find(|&&c| marker_square(c).is_none()) {
+            return invalid(format!("unknown square marker '{}' on the board", c));
+        }
+        if self.start.0 >= size || self.start.1 >= size {
+            return invalid("the start square is outside the board".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn size(&self) -> usize {
+        // Nombre de rangées (et de colonnes) de la grille
+        self.board_rows().len()
+    }
+
+    pub fn premiums(&self) -> Vec<Vec<Square>> {
+        // Cases de la grille vide, avec leurs bonus
+        self.board_rows()
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&c| marker_square(c).unwrap_or(Square::Blank))
+                    .collect()
+            })
+            .collect()
+    }
+
+    pub fn letters(&self) -> impl Iterator<Item = Tile> {
+        // Jetons de l'alphabet, sans le joker
+        (0..self.alphabet.len()).map(|k| k as Tile)
+    }
+
+    pub fn tile(&self, name: &str) -> Option<Tile> {
+        // Jeton correspondant à un nom de lettre comme "A" ou "CH", '?' étant le joker
+        if name == "?" {
+            return Some(BLANK);
+        }
+        let name = name.to_uppercase();
+        self.alphabet
+            .iter()
+            .position(|letter| *letter == name)
+            .map(|k| k as Tile)
+    }
+
+    pub fn name(&self, tile: Tile) -> &str {
+        // Nom d'un jeton, '?' pour le joker ou un jeton inconnu
+        self.alphabet.get(tile as usize).map_or("?", String::as_str)
+    }
+
+    pub fn value(&self, tile: Tile) -> usize {
+        // Valeur d'un jeton, nulle pour le joker ou un jeton inconnu
+        self.tiles.get(self.name(tile)).map_or(0, |info| info.value)
+    }
+
+    pub fn letter_value(&self, letter: Letter) -> usize {
+        // Valeur d'un jeton posé, nulle pour un joker
+        if letter.blank {
+            0
+        } else {
+            self.value(letter.tile)
+        }
+    }
+
+    pub fn rack_value(&self, rack: &HashMap<Tile, usize>) -> usize {
+        // Somme des valeurs des jetons d'un chevalet, décomptée en fin de partie
+        rack.iter()
+            .map(|(&tile, &count)| self.value(tile) * count)
+            .sum()
+    }
+
+    pub fn count(&self, tile: Tile) -> usize {
+        self.tiles.get(self.name(tile)).map_or(0, |info| info.count)
+    }
+
+    pub fn tiles_nb(&self) -> usize {
+        self.tiles.values().map(|tile| tile.count).sum()
+    }
+
+    pub fn bingo_bonus(&self, placed: usize) -> usize {
+        self.bingo.get(&placed).copied().unwrap_or(0)
+    }
+
+    pub fn is_vowel(&self, tile: Tile) -> bool {
+        self.vowels.iter().any(|name| name == self.name(tile))
+    }
+
+    pub fn is_consonant(&self, tile: Tile) -> bool {
+        self.consonants.iter().any(|name| name == self.name(tile))
+    }
+
+    fn longest_tile(&self, text: &str) -> Option<(Tile, usize)> {
+        // Lettre la plus longue de l'alphabet au début de text, écrite en majuscules,
+        // avec sa longueur en octets
+        self.alphabet
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| text.starts_with(name.as_str()))
+            .max_by_key(|(_, name)| name.len())
+            .map(|(k, name)| (k as Tile, name.len()))
+    }
+
+    pub fn read_letter(&self, text: &str) -> Option<(Letter, usize)> {
+        // Lit la lettre la plus longue au début de text avec sa longueur : en majuscules c'est
+        // un jeton, en minuscules un joker, les deux casses ne se mélangeant pas dans une lettre
+        let upper = self
+            .longest_tile(text)
+            .map(|(tile, len)| (Letter { tile, blank: false }, len));
+        let lower = self
+            .alphabet
+            .iter()
+            .map(|name| name.to_lowercase())
+            .enumerate()
+            .filter(|(_, name)| text.starts_with(name.as_str()))
+            .max_by_key(|(_, name)| name.len())
+            .map(|(k, name)| {
+                let tile = k as Tile;
+                (Letter { tile, blank: true }, name.len())
+            });
+        match (upper, lower) {
+            (Some(upper), Some(lower)) if lower.1 > upper.1 => Some(lower),
+            (Some(upper), _) => Some(upper),
+            (None, lower) => lower,
+        }
+    }
+
+    pub fn parse_tiles(&self, text: &str) -> Result<Vec<Tile>, char> {
+        // Découpe un mot en majuscules en jetons, en prenant toujours la lettre la plus longue ;
+        // renvoie sinon le premier caractère qui ne commence aucune lettre de l'alphabet
+        let mut tiles = Vec::new();
+        let mut rest = text;
+        while let Some(c) = rest.chars().next() {
+            let (tile, len) = self.longest_tile(rest).ok_or(c)?;
+            tiles.push(tile);
+            rest = &rest[len..];
+        }
+        Ok(tiles)
+    }
+
+    pub fn normalize(&self, text: &str) -> String {
+        // Met un mot brut sous la forme de l'alphabet : sans espaces autour, en majuscules,
+        // avec les remplacements des règles, et sans les accents des caractères qui
+        // n'apparaissent dans aucune lettre, É devenant E mais Ñ restant Ñ en espagnol
+        let mut word = text.trim().to_uppercase();
+        for (from, to) in &self.replacements {
+            word = word.replace(from.as_str(), to);
+        }
+        word.chars()
+            .flat_map(|c| {
+                let kept = self.alphabet.iter().any(|name| name.contains(c));
+                let decomposed: Vec<char> = if kept {
+                    vec![c]
+                } else {
+                    c.to_string()
+                        .nfd()
+                        .filter(|&d| !is_combining_mark(d))
+                        .collect()
+                };
+                decomposed
+            })
+            .collect()
+    }
+
+    pub fn normalize_word(&self, text: &str) -> Result<Vec<Tile>, char> {
+        // Découpe en jetons un mot d'une liste brute, après l'avoir normalisé
+        self.parse_tiles(&self.normalize(text))
+    }
+
+    pub fn parse_letters(&self, text: &str) -> Result<Vec<Letter>, char> {
+        // Comme parse_tiles, les lettres en minuscules étant des jokers
+        let mut letters = Vec::new();
+        let mut rest = text;
+        while let Some(c) = rest.chars().next() {
+            let (letter, len) = self.read_letter(rest).ok_or(c)?;
+            letters.push(letter);
+            rest = &rest[len..];
+        }
+        Ok(letters)
+    }
+
+    pub fn parse_rack(&self, text: &str) -> Result<HashMap<Tile, usize>, char> {
+        // Transforme une chaîne comme "EGTXYA?" en chevalet, sans tenir compte de la casse
+        let text = text.to_uppercase();
+        let mut rack = HashMap::new();
+        let mut rest = text.as_str();
+        while let Some(c) = rest.chars().next() {
+            let (tile, len) = match c {
+                '?' => (BLANK, 1),
+                _ => self.longest_tile(rest).ok_or(c)?,
+            };
+            *rack.entry(tile).or_insert(0) += 1;
+            rest = &rest[len..];
+        }
+        Ok(rack)
+    }
+
+    pub fn letter_text(&self, letter: Letter) -> String {
+        // Lettre telle qu'on l'écrit, en minuscules pour un joker
+        let name = self.name(letter.tile);
+        if letter.blank {
+            name.to_lowercase()
+        } else {
+            name.to_string()
+        }
+    }
+
+    pub fn word_text(&self, letters: &[Letter]) -> String {
+        letters
+            .iter()
+            .map(|&letter| self.letter_text(letter))
+            .collect()
+    }
+
+    pub fn tiles_text(&self, tiles: &[Tile]) -> String {
+        tiles.iter().map(|&tile| self.name(tile)).collect()
+    }
+
+    pub fn rack_text(&self, rack: &HashMap<Tile, usize>) -> String {
+        // Lettres d'un chevalet, les jokers en premier puis dans l'ordre de l'alphabet
+        let mut tiles: Vec<Tile> = rack
+            .iter()
+            .flat_map(|(&tile, &n)| std::iter::repeat_n(tile, n))
+            .collect();
+        tiles.sort_unstable_by_key(|&tile| (tile != BLANK, tile));
+        self.tiles_text(&tiles)
+    }
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        RuleSet::french().as_ref().clone()
+    }
+}
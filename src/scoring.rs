@@ -0,0 +1,288 @@
+use std::fmt;
+
+use crate::gaddag::Gaddag;
+use crate::grid::{Grid, MoveError, Square};
+use crate::moves::{Direction, Move, Score};
+use crate::rules::Letter;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LetterScore {
+    pub row: usize,
+    pub col: usize,
+    // Lettre telle qu'elle apparaît sur la grille, en minuscules pour un joker
+    pub letter: String,
+    pub value: usize,
+    pub letter_multiplier: usize,
+    // Case avant le coup : une case bonus si le jeton vient d'être posé, sinon la lettre
+    pub square: Square,
+}
+
+impl LetterScore {
+    pub fn points(&self) -> usize {
+        self.value * self.letter_multiplier
+    }
+
+    pub fn is_placed(&self) -> bool {
+        // Vrai si le jeton est posé par le coup, faux s'il était déjà sur la grille
+        !matches!(self.square, Square::Letter(_))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordScore {
+    pub row: usize,
+    pub col: usize,
+    pub direction: Direction,
+    pub word: String,
+    pub letters: Vec<LetterScore>,
+    pub word_multiplier: usize,
+}
+
+impl WordScore {
+    pub fn letters_total(&self) -> usize {
+        self.letters.iter().map(LetterScore::points).sum()
+    }
+
+    pub fn total(&self) -> usize {
+        self.letters_total() * self.word_multiplier
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScoreBreakdown {
+    pub main: WordScore,
+    pub crosswords: Vec<WordScore>,
+    pub bingo: usize,
+}
+
+impl ScoreBreakdown {
+    pub fn crosswords_total(&self) -> usize {
+        self.crosswords.iter().map(WordScore::total).sum()
+    }
+
+    pub fn total(&self) -> usize {
+        self.main.total() + self.crosswords_total() + self.bingo
+    }
+
+    pub fn to_score(&self) -> Score {
+        Score {
+            main: self.main.total(),
+            cross: self.crosswords_total(),
+            bingo: self.bingo,
+        }
+    }
+}
+
+pub(crate) fn square_name(square: Square) -> Option<&'static str> {
+    match square {
+        Square::LCD => Some("LCD"),
+        Square::LCT => Some("LCT"),
+        Square::LCQ => Some("LCQ"),
+        Square::MCD => Some("MCD"),
+        Square::MCT => Some("MCT"),
+        Square::MCQ => Some("MCQ"),
+        _ => None,
+    }
+}
+
+fn score_word(
+    grid: &Grid,
+    row: usize,
+    col: usize,
+    direction: Direction,
+    word: &[Letter],
+) -> WordScore {
+    // Détaille le score d'un mot, les bonus ne comptant que pour les cases encore libres
+    let mut letters = Vec::new();
+    let mut word_multiplier = 1;
+    for (k, &letter) in word.iter().enumerate() {
+        let (i, j) = direction.offset(row, col, k);
+        let (letter_multiplier, multiplier) = grid.get_square_multiplier(i, j);
+        word_multiplier *= multiplier;
+        letters.push(LetterScore {
+            row: i,
+            col: j,
+            letter: grid.rules().letter_text(letter),
+            value: grid.rules().letter_value(letter),
+            letter_multiplier,
+            square: grid.squares[i][j],
+        });
+    }
+    WordScore {
+        row,
+        col,
+        direction,
+        word: grid.rules().word_text(word),
+        letters,
+        word_multiplier,
+    }
+}
+
+fn crossword(
+    grid: &Grid,
+    i: usize,
+    j: usize,
+    letter: Letter,
+    direction: Direction,
+) -> Option<WordScore> {
+    // Détaille le mot formé dans direction par letter posée en (i, j), s'il existe
+    let word = grid.word_through(i, j, letter, direction);
+    if word.len() < 2 {
+        return None;
+    }
+    let (row, col) = grid.word_start(i, j, direction);
+    Some(score_word(grid, row, col, direction, &word))
+}
+
+pub fn score_move(grid: &Grid, mv: &Move, gaddag: &Gaddag) -> Result<ScoreBreakdown, MoveError> {
+    // Détaille le score d'un coup légal : mot principal, mots croisés et prime de scrabble
+    grid.validate_move(mv, gaddag)?;
+    // Les lettres déjà sur la grille sont reprises telles quelles, pour ne pas compter un joker
+    let word: Vec<Letter> = grid
+        .cells(mv)?
+        .into_iter()
+        .map(|(i, j, letter)| grid.letter(i, j).unwrap_or(letter))
+        .collect();
+    let main = score_word(grid, mv.row, mv.col, mv.direction, &word);
+    let placed = grid.placed_tiles(mv)?;
+    let crosswords = placed
+        .iter()
+        .filter_map(|tile| {
+            let letter = Letter {
+                tile: tile.letter,
+                blank: tile.blank,
+            };
+            crossword(grid, tile.row, tile.col, letter, mv.direction.other())
+        })
+        .collect();
+    let bingo = grid.rules().bingo_bonus(placed.len());
+    Ok(ScoreBreakdown {
+        main,
+        crosswords,
+        bingo,
+    })
+}
+
+impl fmt::Display for WordScore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Exemple : "KALIS (H4): K 10 + A 1x2 (LCD) + L 1 + I 1 + S 1 = 14, x2 (MCD) = 28"
+        let coordinates = Grid::pos_to_ref((self.row, self.col), self.direction);
+        write!(f, "{} ({}): ", self.word, coordinates)?;
+        for (k, letter) in self.letters.iter().enumerate() {
+            if k > 0 {
+                write!(f, " + ")?;
+            }
+            write!(f, "{} {}", letter.letter, letter.value)?;
+            if letter.letter_multiplier > 1 {
+                let name = square_name(letter.square).unwrap_or("");
+                write!(f, "x{} ({})", letter.letter_multiplier, name)?;
+            }
+        }
+        write!(f, " = {}", self.letters_total())?;
+        if self.word_multiplier > 1 {
+            write!(f, ", x{}", self.word_multiplier)?;
+            let names: Vec<&str> = self
+                .letters
+                .iter()
+                .filter_map(|l| square_name(l.square))
+                .filter(|name| name.starts_with('M'))
+                .collect();
+            write!(f, " ({})", names.join(", "))?;
+            write!(f, " = {}", self.total())?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ScoreBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Main word: {}", self.main)?;
+        for crossword in &self.crosswords {
+            writeln!(f, "Cross-word: {}", crossword)?;
+        }
+        if self.bingo > 0 {
+            writeln!(f, "Bingo bonus: {}", self.bingo)?;
+        }
+        write!(f, "Total: {}", self.total())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::RuleSet;
+
+    fn test_gaddag() -> Gaddag {
+        Gaddag::from_words(&["ENTRAIS", "SAMA", "AS", "SA"], "test", &RuleSet::french())
+    }
+
+    fn empty_grid() -> Grid {
+        let mut grid = Grid::new();
+        grid.generate_grid();
+        grid
+    }
+
+    #[test]
+    fn bingo_on_the_first_move() {
+        // ENTRAIS en H4 : E sur une lettre compte double, x2 par la case centrale, +50
+        let gaddag = test_gaddag();
+        let grid = empty_grid();
+        let mv = Move::new(7, 3, Direction::Horizontal, "ENTRAIS");
+        let breakdown = score_move(&grid, &mv, &gaddag).unwrap();
+        assert_eq!(breakdown.main.letters_total(), 8);
+        assert_eq!(breakdown.main.word_multiplier, 2);
+        assert_eq!(breakdown.main.total(), 16);
+        assert!(breakdown.crosswords.is_empty());
+        assert_eq!(breakdown.bingo, 50);
+        assert_eq!(breakdown.total(), 66);
+        assert_eq!(
+            breakdown.to_string(),
+            "Main word: ENTRAIS (H4): E 1x2 (LCD) + N 1 + T 1 + R 1 + A 1 + I 1 + S 1 = 8, \
+             x2 (MCD) = 16\nBingo bonus: 50\nTotal: 66"
+        );
+    }
+
+    #[test]
+    fn crosswords_are_scored_separately() {
+        // AS en I8 sous SAMA : le S est sur une lettre compte double, les bonus déjà couverts
+        // ne comptent plus
+        let gaddag = test_gaddag();
+        let mut grid = empty_grid();
+        grid.play(&Move::new(7, 7, Direction::Horizontal, "SAMA"), &gaddag)
+            .unwrap();
+        let mv = Move::new(8, 7, Direction::Horizontal, "AS");
+        let breakdown = score_move(&grid, &mv, &gaddag).unwrap();
+        assert_eq!(breakdown.main.total(), 3);
+        let crosswords: Vec<(String, usize)> = breakdown
+            .crosswords
+            .iter()
+            .map(|w| (w.word.clone(), w.total()))
+            .collect();
+        assert_eq!(crosswords, [("SA".to_string(), 2), ("AS".to_string(), 3)]);
+        assert!(!breakdown.crosswords[0].letters[0].is_placed());
+        assert!(breakdown.crosswords[0].letters[1].is_placed());
+        assert_eq!(breakdown.bingo, 0);
+        assert_eq!(breakdown.total(), 8);
+    }
+
+    #[test]
+    fn blanks_score_nothing() {
+        let gaddag = test_gaddag();
+        let grid = empty_grid();
+        let mv = Move::new(7, 3, Direction::Horizontal, "eNTRAIS");
+        let breakdown = score_move(&grid, &mv, &gaddag).unwrap();
+        assert_eq!(breakdown.main.letters[0].points(), 0);
+        assert_eq!(breakdown.total(), 62);
+    }
+
+    #[test]
+    fn illegal_moves_are_not_scored() {
+        let gaddag = test_gaddag();
+        let grid = empty_grid();
+        let mv = Move::new(0, 0, Direction::Horizontal, "SA");
+        assert_eq!(
+            score_move(&grid, &mv, &gaddag),
+            Err(MoveError::CenterNotCovered)
+        );
+    }
+}
@@ -0,0 +1,502 @@
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+
+use crate::gaddag::Gaddag;
+use crate::grid::Grid;
+use crate::import::grid_from_letters;
+use crate::rules::RuleSet;
+
+// Modèles des lettres A à Z, rendus en gras puis réduits à TEMPLATE_WIDTH x TEMPLATE_HEIGHT
+// cases dont chaque chiffre donne la proportion d'encre, de 0 à 9
+const TEMPLATES: &str = include_str!("letter_templates.txt");
+const TEMPLATE_WIDTH: usize = 12;
+const TEMPLATE_HEIGHT: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScreenshotError {
+    Image(String),
+    BoardNotFound,
+    UnreadableTile { row: usize, col: usize },
+}
+
+impl fmt::Display for ScreenshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScreenshotError::Image(error) => write!(f, "cannot decode the image: {}", error),
+            ScreenshotError::BoardNotFound => write!(f, "no board found in the image"),
+            ScreenshotError::UnreadableTile { row, col } => write!(
+                f,
+                "cannot read the tile at row {}, column {}",
+                (b'A' + *row as u8) as char,
+                col + 1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ScreenshotError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileReading {
+    pub row: usize,
+    pub col: usize,
+    // Lettre reconnue, en minuscule pour un joker
+    pub letter: char,
+    // Distance au modèle le plus proche : plus elle est faible, plus la lecture est sûre
+    pub distance: f64,
+}
+
+struct Picture {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 3]>,
+}
+
+impl Picture {
+    fn open(filename: &str) -> Result<Self, ScreenshotError> {
+        // Décode une image PNG en pixels RGB
+        let error = |e: &dyn std::error::Error| ScreenshotError::Image(e.to_string());
+        let file = File::open(filename).map_err(|e| error(&e))?;
+        let mut decoder = png::Decoder::new(BufReader::new(file));
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(|e| error(&e))?;
+        let size = reader
+            .output_buffer_size()
+            .ok_or_else(|| ScreenshotError::Image("image too large".to_string()))?;
+        let mut buffer = vec![0; size];
+        let info = reader.next_frame(&mut buffer).map_err(|e| error(&e))?;
+        let channels = info.color_type.samples();
+        let (width, height) = (info.width as usize, info.height as usize);
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let line = &buffer[y * info.line_size..];
+            for x in 0..width {
+                let p = &line[x * channels..(x + 1) * channels];
+                pixels.push(match channels {
+                    1 | 2 => [p[0], p[0], p[0]],
+                    _ => [p[0], p[1], p[2]],
+                });
+            }
+        }
+        Ok(Picture {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> [u8; 3] {
+        self.pixels[y * self.width + x]
+    }
+}
+
+fn is_board_green(p: [u8; 3]) -> bool {
+    // Vert des cases sans bonus
+    let [r, g, b] = p.map(i32::from);
+    g > r + 40 && g > b + 25
+}
+
+fn is_tile(p: [u8; 3]) -> bool {
+    // Fond beige des jetons
+    let [r, g, b] = p.map(i32::from);
+    r > 180 && g > 170 && r - b > 40 && (r - g).abs() < 45
+}
+
+fn is_ink(p: [u8; 3]) -> bool {
+    let [r, g, b] = p.map(u32::from);
+    r * 299 + g * 587 + b * 114 < 110_000
+}
+
+fn longest_run(counts: &[usize], size: usize) -> Option<(usize, usize)> {
+    // Plus longue suite de valeurs supérieures au tiers du maximum, (début, fin exclue) ;
+    // les creux de quelques pixels, comme les lignes de la grille, ne l'interrompent pas
+    const GAP: usize = 6;
+    let threshold = counts.iter().max().copied().unwrap_or(0) / 3;
+    let mut best: Option<(usize, usize)> = None;
+    let mut run: Option<(usize, usize)> = None;
+    for (k, _) in counts.iter().enumerate().filter(|(_, &c)| c > threshold) {
+        run = match run {
+            Some((start, end)) if k - end <= GAP => Some((start, k + 1)),
+            _ => Some((k, k + 1)),
+        };
+        let (start, end) = run.unwrap();
+        if best.is_none_or(|(a, b)| end - start > b - a) {
+            best = run;
+        }
+    }
+    best.filter(|(a, b)| b - a >= size)
+}
+
+fn locate_board(
+    picture: &Picture,
+    size: usize,
+) -> Result<(usize, usize, usize, usize), ScreenshotError> {
+    // Cherche le plateau comme la plus grande zone de colonnes puis de lignes riches en vert
+    let mut columns = vec![0; picture.width];
+    for y in 0..picture.height {
+        for (x, column) in columns.iter_mut().enumerate() {
+            if is_board_green(picture.pixel(x, y)) {
+                *column += 1;
+            }
+        }
+    }
+    let (x0, x1) = longest_run(&columns, size).ok_or(ScreenshotError::BoardNotFound)?;
+    let rows: Vec<usize> = (0..picture.height)
+        .map(|y| {
+            (x0..x1)
+                .filter(|&x| is_board_green(picture.pixel(x, y)))
+                .count()
+        })
+        .collect();
+    let (y0, y1) = longest_run(&rows, size).ok_or(ScreenshotError::BoardNotFound)?;
+    // Les cases doivent être à peu près carrées
+    let (width, height) = (x1 - x0, y1 - y0);
+    if width.abs_diff(height) * 10 > width {
+        return Err(ScreenshotError::BoardNotFound);
+    }
+    Ok((x0, y0, width, height))
+}
+
+fn components(mask: &[bool], width: usize) -> Vec<Vec<usize>> {
+    // Composantes connexes (4-connexité) des pixels d'encre
+    let height = mask.len() / width;
+    let mut seen = vec![false; mask.len()];
+    let mut result = Vec::new();
+    for start in 0..mask.len() {
+        if !mask[start] || seen[start] {
+            continue;
+        }
+        seen[start] = true;
+        let mut stack = vec![start];
+        let mut component = Vec::new();
+        while let Some(k) = stack.pop() {
+            component.push(k);
+            let (x, y) = (k % width, k / width);
+            let mut neighbours = Vec::with_capacity(4);
+            if x > 0 {
+                neighbours.push(k - 1);
+            }
+            if x + 1 < width {
+                neighbours.push(k + 1);
+            }
+            if y > 0 {
+                neighbours.push(k - width);
+            }
+            if y + 1 < height {
+                neighbours.push(k + width);
+            }
+            for n in neighbours {
+                if mask[n] && !seen[n] {
+                    seen[n] = true;
+                    stack.push(n);
+                }
+            }
+        }
+        result.push(component);
+    }
+    result
+}
+
+fn bounding_box(component: &[usize], width: usize) -> (usize, usize, usize, usize) {
+    // (x min, y min, x max exclu, y max exclu)
+    component
+        .iter()
+        .fold((usize::MAX, usize::MAX, 0, 0), |(x0, y0, x1, y1), &k| {
+            let (x, y) = (k % width, k / width);
+            (x0.min(x), y0.min(y), x1.max(x + 1), y1.max(y + 1))
+        })
+}
+
+struct Shape {
+    // Encre de chaque case de la grille des modèles, de 0 à 9, adoucie sur les cases voisines
+    grid: Vec<f64>,
+    // Rapport largeur / hauteur
+    ratio: f64,
+    // Nombre de trous, comme les deux boucles du B
+    holes: usize,
+}
+
+fn blur(grid: &[f64]) -> Vec<f64> {
+    // Moyenne sur les cases voisines, pour tolérer les petites différences entre polices
+    (0..grid.len())
+        .map(|k| {
+            let (x, y) = (k % TEMPLATE_WIDTH, k / TEMPLATE_WIDTH);
+            let (mut sum, mut n) = (0.0, 0.0);
+            for ny in y.saturating_sub(1)..(y + 2).min(TEMPLATE_HEIGHT) {
+                for nx in x.saturating_sub(1)..(x + 2).min(TEMPLATE_WIDTH) {
+                    sum += grid[ny * TEMPLATE_WIDTH + nx];
+                    n += 1.0;
+                }
+            }
+            sum / n
+        })
+        .collect()
+}
+
+fn holes(bitmap: &[bool], w: usize, h: usize) -> usize {
+    // Compte les zones vides entourées d'encre, en ignorant celles de moins de 1 % de la lettre
+    let (pw, ph) = (w + 2, h + 2);
+    let background: Vec<bool> = (0..pw * ph)
+        .map(|k| {
+            let (x, y) = (k % pw, k / pw);
+            x == 0 || y == 0 || x > w || y > h || !bitmap[(y - 1) * w + x - 1]
+        })
+        .collect();
+    components(&background, pw)
+        .iter()
+        .filter(|component| !component.contains(&0) && component.len() * 100 >= w * h)
+        .count()
+}
+
+fn shape(component: &[usize], width: usize) -> Shape {
+    // Réduit la lettre à la grille des modèles ; chaque case de la grille couvre au moins
+    // un pixel, même pour une lettre étroite
+    let (x0, y0, x1, y1) = bounding_box(component, width);
+    let (w, h) = (x1 - x0, y1 - y0);
+    let mut bitmap = vec![false; w * h];
+    for &k in component {
+        bitmap[(k / width - y0) * w + k % width - x0] = true;
+    }
+    let span = |g: usize, size: usize, cells: usize| {
+        let start = g * size / cells;
+        (start, ((g + 1) * size / cells).max(start + 1).min(size))
+    };
+    let mut grid = Vec::with_capacity(TEMPLATE_WIDTH * TEMPLATE_HEIGHT);
+    for gy in 0..TEMPLATE_HEIGHT {
+        let (ya, yb) = span(gy, h, TEMPLATE_HEIGHT);
+        for gx in 0..TEMPLATE_WIDTH {
+            let (xa, xb) = span(gx, w, TEMPLATE_WIDTH);
+            let ink = (ya..yb)
+                .flat_map(|y| (xa..xb).map(move |x| (x, y)))
+                .filter(|&(x, y)| bitmap[y * w + x])
+                .count();
+            grid.push(9.0 * ink as f64 / ((yb - ya) * (xb - xa)) as f64);
+        }
+    }
+    Shape {
+        grid: blur(&grid),
+        ratio: w as f64 / h as f64,
+        holes: holes(&bitmap, w, h),
+    }
+}
+
+fn load_templates() -> Vec<(char, Shape)> {
+    // Lit les modèles : une ligne "lettre rapport trous" suivie de TEMPLATE_HEIGHT lignes
+    // de chiffres
+    let mut templates = Vec::new();
+    let mut lines = TEMPLATES
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+    while let Some(header) = lines.next() {
+        let mut parts = header.split_whitespace();
+        let letter = parts.next().and_then(|s| s.chars().next()).unwrap_or('?');
+        let ratio = parts.next().and_then(|s| s.parse().ok()).unwrap_or(1.0);
+        let holes = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0);
+        let grid: Vec<f64> = lines
+            .by_ref()
+            .take(TEMPLATE_HEIGHT)
+            .flat_map(|line| line.chars().map(|c| c.to_digit(10).unwrap_or(0) as f64))
+            .collect();
+        let grid = blur(&grid);
+        templates.push((letter, Shape { grid, ratio, holes }));
+    }
+    templates
+}
+
+fn classify(letter: &Shape, templates: &[(char, Shape)]) -> (char, f64) {
+    // Modèle le plus proche ; le nombre de trous sépare C et O, le rapport largeur / hauteur
+    // I et L
+    templates
+        .iter()
+        .map(|(c, template)| {
+            let shape: f64 = letter
+                .grid
+                .iter()
+                .zip(&template.grid)
+                .map(|(a, b)| (a - b) * (a - b))
+                .sum();
+            let distance = shape.sqrt() / 9.0
+                + 2.0 * (letter.ratio - template.ratio).abs()
+                + 3.0 * letter.holes.abs_diff(template.holes) as f64;
+            (*c, distance)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or(('?', f64::INFINITY))
+}
+
+fn read_tile(
+    picture: &Picture,
+    (x0, y0, x1, y1): (usize, usize, usize, usize),
+    templates: &[(char, Shape)],
+) -> Option<(char, f64)> {
+    // Lit la lettre d'un jeton ; un jeton sans valeur en indice est un joker
+    let width = x1 - x0;
+    let mask: Vec<bool> = (y0..y1)
+        .flat_map(|y| (x0..x1).map(move |x| (x, y)))
+        .map(|(x, y)| is_ink(picture.pixel(x, y)))
+        .collect();
+    let parts = components(&mask, width);
+    let letter = parts.iter().max_by_key(|component| {
+        let (_, top, _, bottom) = bounding_box(component, width);
+        (bottom - top, component.len())
+    })?;
+    let (lx0, ly0, lx1, ly1) = bounding_box(letter, width);
+    if (ly1 - ly0) * 4 < y1 - y0 {
+        return None;
+    }
+    // La valeur est écrite en petit, en bas à droite de la lettre
+    let has_value = parts.iter().any(|component| {
+        let (cx0, cy0, _, cy1) = bounding_box(component, width);
+        let height = cy1 - cy0;
+        height * 10 >= y1 - y0 && height < ly1 - ly0 && cx0 >= (lx0 + lx1) / 2 && cy0 > ly0
+    });
+    let (c, distance) = classify(&shape(letter, width), templates);
+    let letter = if has_value { c } else { c.to_ascii_lowercase() };
+    Some((letter, distance))
+}
+
+fn square_box(
+    (bx, by, width, height): (usize, usize, usize, usize),
+    size: usize,
+    row: usize,
+    col: usize,
+) -> (usize, usize, usize, usize) {
+    // Retourne l'intérieur de la case (row, col) du plateau, sans son bord où se trouvent les
+    // lignes de la grille
+    let x0 = bx + col * width / size;
+    let y0 = by + row * height / size;
+    let x1 = bx + (col + 1) * width / size;
+    let y1 = by + (row + 1) * height / size;
+    let (mx, my) = ((x1 - x0) / 10, (y1 - y0) / 10);
+    (x0 + mx, y0 + my, x1 - mx, y1 - my)
+}
+
+pub fn read_screenshot(filename: &str, size: usize) -> Result<Vec<TileReading>, ScreenshotError> {
+    // Repère le plateau de size x size cases dans une capture d'écran et lit chacun des
+    // jetons posés
+    let picture = Picture::open(filename)?;
+    let board = locate_board(&picture, size)?;
+    let templates = load_templates();
+    let mut readings = Vec::new();
+    for row in 0..size {
+        for col in 0..size {
+            let inner = square_box(board, size, row, col);
+            let total = (inner.2 - inner.0) * (inner.3 - inner.1);
+            let beige = (inner.1..inner.3)
+                .flat_map(|y| (inner.0..inner.2).map(move |x| (x, y)))
+                .filter(|&(x, y)| is_tile(picture.pixel(x, y)))
+                .count();
+            if beige * 3 < total {
+                continue;
+            }
+            let (letter, distance) = read_tile(&picture, inner, &templates)
+                .ok_or(ScreenshotError::UnreadableTile { row, col })?;
+            readings.push(TileReading {
+                row,
+                col,
+                letter,
+                distance,
+            });
+        }
+    }
+    Ok(readings)
+}
+
+pub fn grid_from_screenshot(
+    filename: &str,
+    rules: &Arc<RuleSet>,
+    gaddag: &Gaddag,
+) -> Result<Grid, ScreenshotError> {
+    // Construit la grille correspondant à une capture d'écran
+    let size = rules.size();
+    let mut letters = vec![vec![None; size]; size];
+    for tile in read_screenshot(filename, size)? {
+        // Les modèles ne connaissent que des lettres d'un caractère, qui doivent être dans
+        // l'alphabet des règles
+        let text = tile.letter.to_string();
+        let letter = match rules.read_letter(&text) {
+            Some((letter, len)) if len == text.len() => letter,
+            _ => {
+                return Err(ScreenshotError::UnreadableTile {
+                    row: tile.row,
+                    col: tile.col,
+                })
+            }
+        };
+        letters[tile.row][tile.col] = Some(letter);
+    }
+    Ok(grid_from_letters(&letters, rules, gaddag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Grille de grille_test.png, à la racine du dépôt
+    const EXPECTED: [&str; 15] = [
+        "...............",
+        "......C........",
+        "......I........",
+        "......B........",
+        "....WALI.......",
+        "TARGUIE........",
+        "......R........",
+        "...GUEEES......",
+        "......Z........",
+        "...............",
+        "...............",
+        "...............",
+        "...............",
+        "...............",
+        "...............",
+    ];
+
+    const SCREENSHOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/grille_test.png");
+
+    #[test]
+    fn screenshot_is_read_as_the_expected_board() {
+        let mut rows = vec![vec!['.'; 15]; 15];
+        for tile in read_screenshot(SCREENSHOT, 15).unwrap() {
+            rows[tile.row][tile.col] = tile.letter;
+        }
+        let rows: Vec<String> = rows.into_iter().map(String::from_iter).collect();
+        assert_eq!(rows, EXPECTED);
+    }
+
+    #[test]
+    fn tile_without_a_value_is_a_blank() {
+        // On efface le chiffre du E en H8 en repeignant l'encre du coin bas droit de la case
+        let mut picture = Picture::open(SCREENSHOT).unwrap();
+        let board = locate_board(&picture, 15).unwrap();
+        let templates = load_templates();
+        let inner = square_box(board, 15, 7, 7);
+        assert_eq!(
+            read_tile(&picture, inner, &templates).map(|r| r.0),
+            Some('E')
+        );
+        let (x0, y0, x1, y1) = inner;
+        let background = picture.pixel((x0 + x1) / 2, y0 + 1);
+        assert!(is_tile(background));
+        for y in y0 + (y1 - y0) * 2 / 3..y1 {
+            for x in x0 + (x1 - x0) * 3 / 4..x1 {
+                if is_ink(picture.pixel(x, y)) {
+                    picture.pixels[y * picture.width + x] = background;
+                }
+            }
+        }
+        assert_eq!(
+            read_tile(&picture, inner, &templates).map(|r| r.0),
+            Some('e')
+        );
+    }
+
+    #[test]
+    fn missing_file_is_an_image_error() {
+        assert!(matches!(
+            read_screenshot("does-not-exist.png", 15),
+            Err(ScreenshotError::Image(_))
+        ));
+    }
+}
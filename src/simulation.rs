@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use rand::random;
+use rayon::prelude::*;
+
+use crate::bag::Bag;
+use crate::gaddag::Gaddag;
+use crate::grid::Grid;
+use crate::leave::LeaveTable;
+use crate::moves::Move;
+use crate::rules::{RuleSet, Tile};
+use crate::solver::generate_solutions;
+use crate::unseen::unseen_tiles;
+
+// Réglages d'une simulation de Monte-Carlo des meilleurs coups
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimConfig {
+    // Nombre de coups candidats, les meilleurs selon leur score et leur reliquat
+    pub candidates: usize,
+    // Nombre de tirages simulés pour chaque candidat
+    pub iterations: usize,
+    // Nombre de coups joués après le candidat, en alternant l'adversaire et nous
+    pub plies: usize,
+    // Avance au score avant le coup, négative si on est mené, comptée pour savoir qui mène
+    // à la fin des tirages
+    pub spread: i64,
+    pub seed: Option<u64>,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        SimConfig {
+            candidates: 10,
+            iterations: 100,
+            plies: 2,
+            spread: 0,
+            seed: None,
+        }
+    }
+}
+
+// Résultat de la simulation d'un candidat
+#[derive(Debug, Clone)]
+pub struct SimResult {
+    pub mv: Move,
+    pub iterations: usize,
+    // Écart de points moyen gagné sur l'adversaire, reliquats finaux compris
+    pub equity: f64,
+    // Erreur type de la moyenne
+    pub std_error: f64,
+    // Part des tirages où l'on mène après les coups simulés, une égalité comptant pour
+    // moitié ; la partie n'étant pas jouée jusqu'au bout, ce n'est pas une probabilité de gain
+    pub ahead_rate: f64,
+}
+
+fn best_move(
+    grid: &Grid,
+    rack: &HashMap<Tile, usize>,
+    gaddag: &Gaddag,
+    leaves: &LeaveTable,
+) -> Option<Move> {
+    // Joueur statique : le meilleur coup selon son score et la valeur de son reliquat
+    let mut moves = generate_solutions(grid, rack, gaddag);
+    leaves.rank(&mut moves);
+    moves.into_iter().next()
+}
+
+struct Playout {
+    // Écart de points gagné sur l'adversaire
+    spread: i64,
+    // Écart corrigé par la valeur des reliquats restants, si la partie n'est pas finie
+    equity: f64,
+}
+
+fn playout(
+    grid: &Grid,
+    candidate: &Move,
+    unseen: &[Tile],
+    gaddag: &Gaddag,
+    leaves: &LeaveTable,
+    config: &SimConfig,
+    seed: u64,
+) -> Playout {
+    // Joue le candidat puis quelques coups statiques sur un tirage des jetons invisibles
+    let rules: Arc<RuleSet> = grid.rules().clone();
+    let mut bag = Bag::from_tiles(rules.clone(), unseen.to_vec(), Some(seed));
+    let mut grid = grid.clone();
+    // L'adversaire tire son rack avant que l'on complète le nôtre
+    let mut racks = [candidate.leave.clone(), HashMap::new()];
+    bag.fill(&mut racks[1], rules.rack_size);
+    grid.place(candidate, gaddag);
+    bag.fill(&mut racks[0], rules.rack_size);
+    let mut spread = candidate.score.total() as i64;
+    // 0 pour nous, 1 pour l'adversaire, qui joue en premier après le candidat
+    let mut player = 1;
+    let mut finished = racks[0].is_empty();
+    for _ in 0..config.plies {
+        if finished {
+            break;
+        }
+        // Un joueur sans coup possible passe son tour
+        if let Some(mv) = best_move(&grid, &racks[player], gaddag, leaves) {
+            let sign = if player == 0 { 1 } else { -1 };
+            grid.place(&mv, gaddag);
+            spread += sign * mv.score.total() as i64;
+            racks[player] = mv.leave.clone();
+            bag.fill(&mut racks[player], rules.rack_size);
+            finished = racks[player].is_empty();
+        }
+        player = 1 - player;
+    }
+    if finished {
+        // Celui qui a fini gagne la valeur des jetons de l'autre, que celui-ci perd
+        spread += 2 * (rules.rack_value(&racks[1]) as i64 - rules.rack_value(&racks[0]) as i64);
+        return Playout {
+            spread,
+            equity: spread as f64,
+        };
+    }
+    Playout {
+        spread,
+        equity: spread as f64 + leaves.value(&racks[0]) - leaves.value(&racks[1]),
+    }
+}
+
+pub fn simulate_moves(
+    grid: &Grid,
+    rack: &HashMap<Tile, usize>,
+    gaddag: &Gaddag,
+    leaves: &LeaveTable,
+    config: &SimConfig,
+) -> Vec<SimResult> {
+    // Simule les meilleurs coups statiques et les classe selon l'écart moyen obtenu ; chaque
+    // candidat est joué sur les mêmes tirages pour que leurs résultats se comparent
+    let mut moves = generate_solutions(grid, rack, gaddag);
+    leaves.rank(&mut moves);
+    moves.truncate(config.candidates);
+    let unseen = unseen_tiles(grid, rack).to_vec();
+    let base_seed = config.seed.unwrap_or_else(random);
+    let mut results: Vec<SimResult> = moves
+        .into_iter()
+        .map(|mv| {
+            let playouts: Vec<Playout> = (0..config.iterations as u64)
+                .into_par_iter()
+                .map(|k| {
+                    let seed = base_seed.wrapping_add(k);
+                    playout(grid, &mv, &unseen, gaddag, leaves, config, seed)
+                })
+                .collect();
+            let n = playouts.len().max(1) as f64;
+            let equity = playouts.iter().map(|p| p.equity).sum::<f64>() / n;
+            let variance = playouts
+                .iter()
+                .map(|p| (p.equity - equity).powi(2))
+                .sum::<f64>()
+                / (n - 1.0).max(1.0);
+            let ahead: f64 = playouts
+                .iter()
+                .map(|p| match (config.spread + p.spread).signum() {
+                    1 => 1.0,
+                    0 => 0.5,
+                    _ => 0.0,
+                })
+                .sum();
+            SimResult {
+                mv,
+                iterations: playouts.len(),
+                equity,
+                std_error: (variance / n).sqrt(),
+                ahead_rate: ahead / n,
+            }
+        })
+        .collect();
+    results.sort_by(|a, b| b.equity.total_cmp(&a.equity));
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::Direction;
+
+    fn test_gaddag() -> Gaddag {
+        Gaddag::from_words(
+            &[
+                "SAMA", "MAS", "AS", "SA", "TAS", "RAS", "ARS", "ART", "RAT", "TAR", "ET", "TE",
+                "ES", "SE", "RE", "MAT", "MATS", "RATS", "ARTS", "STAR",
+            ],
+            "test",
+            &RuleSet::french(),
+        )
+    }
+
+    fn test_grid(gaddag: &Gaddag) -> Grid {
+        let mut grid = Grid::new();
+        grid.generate_grid();
+        grid.play(&Move::new(7, 7, Direction::Horizontal, "SAMA"), gaddag)
+            .unwrap();
+        grid
+    }
+
+    fn summary(results: &[SimResult]) -> Vec<(String, String, f64, f64)> {
+        results
+            .iter()
+            .map(|r| {
+                (
+                    r.mv.coordinates(),
+                    r.mv.word.clone(),
+                    r.equity,
+                    r.ahead_rate,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn seeded_simulation_is_reproducible() {
+        let gaddag = test_gaddag();
+        let grid = test_grid(&gaddag);
+        let rack = RuleSet::french().parse_rack("ARTSE").unwrap();
+        let config = SimConfig {
+            candidates: 3,
+            iterations: 6,
+            plies: 2,
+            spread: 0,
+            seed: Some(7),
+        };
+        let leaves = LeaveTable::new();
+        let first = simulate_moves(&grid, &rack, &gaddag, &leaves, &config);
+        let second = simulate_moves(&grid, &rack, &gaddag, &leaves, &config);
+        assert_eq!(summary(&first), summary(&second));
+        for pair in first.windows(2) {
+            assert!(pair[0].equity >= pair[1].equity);
+        }
+        for result in &first {
+            assert!((0.0..=1.0).contains(&result.ahead_rate));
+        }
+    }
+
+    #[test]
+    fn candidates_and_iterations_are_respected() {
+        let gaddag = test_gaddag();
+        let grid = test_grid(&gaddag);
+        let rack = RuleSet::french().parse_rack("ARTSE").unwrap();
+        let leaves = LeaveTable::new();
+        let mut moves = generate_solutions(&grid, &rack, &gaddag);
+        leaves.rank(&mut moves);
+        let config = SimConfig {
+            candidates: 4,
+            iterations: 5,
+            seed: Some(1),
+            ..SimConfig::default()
+        };
+        let results = simulate_moves(&grid, &rack, &gaddag, &leaves, &config);
+        assert_eq!(results.len(), 4);
+        assert!(results.iter().all(|result| result.iterations == 5));
+        // Les candidats sont les quatre meilleurs coups statiques
+        let mut simulated: Vec<(String, String)> = results
+            .iter()
+            .map(|r| (r.mv.coordinates(), r.mv.word.clone()))
+            .collect();
+        let mut best: Vec<(String, String)> = moves[..4]
+            .iter()
+            .map(|m| (m.coordinates(), m.word.clone()))
+            .collect();
+        simulated.sort();
+        best.sort();
+        assert_eq!(simulated, best);
+        // Avec plus de candidats demandés que de coups, tous les coups sont simulés
+        let config = SimConfig {
+            candidates: moves.len() + 5,
+            iterations: 1,
+            seed: Some(1),
+            ..SimConfig::default()
+        };
+        let results = simulate_moves(&grid, &rack, &gaddag, &leaves, &config);
+        assert_eq!(results.len(), moves.len());
+    }
+}
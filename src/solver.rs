@@ -1,37 +1,27 @@
 use std::cmp::min;
 use std::collections::HashMap;
-use std::rc::Rc;
 
-use crate::constants::{BINGOS_BONUS, GRID_SIZE, LETTERS_VALUE};
-use crate::gaddag::{Gaddag, GaddagNode};
+use rayon::prelude::*;
+
+use crate::gaddag::{Gaddag, NodeId, SEPARATOR};
 use crate::grid::{Grid, Square};
+use crate::moves::{Direction, Move, PlacedTile, Score};
+use crate::rack::remove_tiles;
+use crate::rules::{Letter, Tile, BLANK};
 
-pub struct WordInfo {
+struct WordInfo {
     pub position: (usize, usize),
-    pub rack: HashMap<char, usize>,
-    pub prefix: String,
+    pub rack: HashMap<Tile, usize>,
+    pub prefix: Vec<Letter>,
     pub score: (usize, usize, usize),
     pub letters_nb: u8,
-    pub node: GaddagNode,
-}
-
-pub struct ValidWord {
-    pub position: String,
-    pub rack: HashMap<char, usize>,
-    pub word: String,
-    pub score: usize,
+    pub node: NodeId,
 }
 
-fn reduce_rack(rack: &HashMap<char, usize>, letter: char) -> HashMap<char, usize> {
+fn reduce_rack(rack: &HashMap<Tile, usize>, letter: Tile) -> HashMap<Tile, usize> {
     // Effectue une copie du rack avec une occurence de letter en moins
     let mut new_rack = rack.clone();
-    if let Some(count) = new_rack.get_mut(&letter) {
-        if *count > 1 {
-            *count -= 1;
-        } else {
-            new_rack.remove(&letter);
-        }
-    }
+    remove_tiles(&mut new_rack, &[letter]);
     new_rack
 }
 
@@ -39,17 +29,18 @@ fn process_letter(
     i: usize,
     j: usize,
     grid: &Grid,
+    gaddag: &Gaddag,
     wordinfo: &WordInfo,
-    letter: char,
-    replacement: char,
+    letter: Tile,
+    replacement: Tile,
 ) -> Option<WordInfo> {
     // Remplacement sert pour le joker
-    if let Some(next_node) = wordinfo.node.borrow().children.get(&replacement) {
+    if let Some(next_node) = gaddag.child(wordinfo.node, replacement) {
         // Vérifie si on ne forme pas un crossword invalide
         let mut new_cw_score = wordinfo.score.2;
         if let Some(cw) = &grid.crosswords[i][j] {
             if let Some(cw_value) = cw.get(&replacement) {
-                if letter == '?' {
+                if letter == BLANK {
                     new_cw_score += cw.get(&letter).unwrap_or(&0);
                 } else {
                     new_cw_score += cw_value;
@@ -61,15 +52,13 @@ fn process_letter(
         // Génère la nouvelle rack et le nouveau prefix
         let new_rack = reduce_rack(&wordinfo.rack, letter);
         let mut new_prefix = wordinfo.prefix.clone();
-        new_prefix.push(if letter == '?' {
-            replacement.to_ascii_lowercase()
-        } else {
-            letter
+        new_prefix.push(Letter {
+            tile: replacement,
+            blank: letter == BLANK,
         });
         // Mise à jour des scores
         let (square_flat, square_mult) = grid.get_square_multiplier(i, j);
-        let new_flat_score =
-            wordinfo.score.0 + LETTERS_VALUE.get(&letter).unwrap_or(&0) * square_flat;
+        let new_flat_score = wordinfo.score.0 + grid.rules().value(letter) * square_flat;
         let new_multiplier = wordinfo.score.1 * square_mult;
         // Retourne le nouveau WordInfo
         return Some(WordInfo {
@@ -78,59 +67,64 @@ fn process_letter(
             prefix: new_prefix,
             score: (new_flat_score, new_multiplier, new_cw_score),
             letters_nb: wordinfo.letters_nb + 1,
-            node: Rc::clone(next_node),
+            node: next_node,
         });
     }
     None
 }
 
-fn step(i: usize, j: usize, grid: &Grid, wordinfo: &WordInfo) -> Vec<WordInfo> {
+fn step(i: usize, j: usize, grid: &Grid, gaddag: &Gaddag, wordinfo: &WordInfo) -> Vec<WordInfo> {
     // Prend un WordInfo et effectue un pas
     let mut results = Vec::new();
     for (&letter, _) in wordinfo.rack.iter() {
-        if letter == '?' {
-            for replacement in 'A'..='Z' {
-                if let Some(result) = process_letter(i, j, grid, wordinfo, letter, replacement) {
+        if letter == BLANK {
+            for replacement in grid.rules().letters() {
+                if let Some(result) =
+                    process_letter(i, j, grid, gaddag, wordinfo, letter, replacement)
+                {
                     results.push(result);
                 }
             }
-        } else if let Some(result) = process_letter(i, j, grid, wordinfo, letter, letter) {
+        } else if let Some(result) = process_letter(i, j, grid, gaddag, wordinfo, letter, letter) {
             results.push(result);
         }
     }
     results
 }
 
-pub fn handle_left_part(
+fn handle_left_part(
     i: usize,
     j: usize,
     grid: &Grid,
+    gaddag: &Gaddag,
     wordinfos: Vec<WordInfo>,
 ) -> Vec<WordInfo> {
     // Récupère le préfixe à gauche de l'ancre
-    let mut left_prefix = String::new();
+    let mut left_prefix = Vec::new();
     let mut left_score = 0;
     let mut k = j;
     while k > 0 {
         if let Square::Letter(letter) = grid.squares[i][k - 1] {
             left_prefix.push(letter);
-            left_score += *LETTERS_VALUE.get(&letter).unwrap_or(&0);
+            left_score += grid.rules().letter_value(letter);
             k -= 1;
         } else {
             break;
         }
     }
-    // Construit les préfixes valides
+    // Construit les préfixes valides, les jokers de la grille étant cherchés par leur lettre
+    let path: Vec<Tile> = left_prefix.iter().map(|letter| letter.tile).collect();
     let mut results = Vec::new();
     for wordinfo in wordinfos {
-        if let Some(node) = Gaddag::follow_path(&wordinfo.node, &left_prefix) {
-            let new_prefix = format!("{}{}", wordinfo.prefix, left_prefix);
+        if let Some(node) = gaddag.follow_path(wordinfo.node, &path) {
+            let mut new_prefix = wordinfo.prefix.clone();
+            new_prefix.extend_from_slice(&left_prefix);
             let new_flat_score = wordinfo.score.0 + left_score;
             results.push(WordInfo {
                 position: (i, k),
                 prefix: new_prefix,
                 score: (new_flat_score, wordinfo.score.1, wordinfo.score.2),
-                node: node,
+                node,
                 ..wordinfo
             });
         }
@@ -138,32 +132,32 @@ pub fn handle_left_part(
     results
 }
 
-pub fn generate_left_parts(
+fn generate_left_parts(
     i: usize,
     j: usize,
     grid: &Grid,
-    rack: &HashMap<char, usize>,
-    gaddag: &GaddagNode,
+    rack: &HashMap<Tile, usize>,
+    gaddag: &Gaddag,
 ) -> Vec<WordInfo> {
     // Retourne l'ensemble des préfixes gauches à partir de (i, j)
     let empty_wordinfo = WordInfo {
         position: (i, j),
         rack: rack.clone(),
-        prefix: String::new(),
+        prefix: Vec::new(),
         score: (0, 1, 0),
         letters_nb: 0,
-        node: Rc::clone(gaddag),
+        node: gaddag.root(),
     };
     // Vérification de la présence d'une lettre à gauche de l'ancre
     if j > 0 {
         if let Square::Letter(_) = grid.squares[i][j - 1] {
-            let current_wordinfos = step(i, j, grid, &empty_wordinfo);
-            return handle_left_part(i, j, grid, current_wordinfos);
+            let current_wordinfos = step(i, j, grid, gaddag, &empty_wordinfo);
+            return handle_left_part(i, j, grid, gaddag, current_wordinfos);
         }
     }
     // Recherche de la place disponible à gauche de l'ancre
     let mut left_limit = j;
-    while left_limit > 0 && grid.anchors[i][left_limit - 1] == false {
+    while left_limit > 0 && !grid.anchors[i][left_limit - 1] {
         left_limit -= 1;
     }
     // Génération des préfixes
@@ -172,7 +166,7 @@ pub fn generate_left_parts(
     let mut next_prefixes = Vec::new();
     for k in (left_limit..=j).rev() {
         for wordinfo in current_prefixes.drain(..) {
-            let results = step(i, k, grid, &wordinfo);
+            let results = step(i, k, grid, gaddag, &wordinfo);
             for result in results {
                 next_prefixes.push(result);
             }
@@ -185,17 +179,17 @@ pub fn generate_left_parts(
     all_results
 }
 
-pub fn filter_left_parts(wordinfos: Vec<WordInfo>) -> Vec<WordInfo> {
+fn filter_left_parts(gaddag: &Gaddag, wordinfos: Vec<WordInfo>) -> Vec<WordInfo> {
     // Filtre les préfixes gauches obtenus en ne gardant que ceux qui peuvent être le début d'un mot
     let mut filtered_wordinfos = Vec::new();
     for wordinfo in wordinfos {
-        if let Some(bang_node) = wordinfo.node.borrow().children.get(&'!') {
+        if let Some(bang_node) = gaddag.child(wordinfo.node, SEPARATOR) {
             // Renversement du préfixe
-            let reversed_prefix = wordinfo.prefix.chars().rev().collect();
+            let reversed_prefix = wordinfo.prefix.iter().rev().copied().collect();
             // Ajout du résultat modifié à la liste filtrée
             filtered_wordinfos.push(WordInfo {
                 prefix: reversed_prefix,
-                node: Rc::clone(bang_node),
+                node: bang_node,
                 ..wordinfo
             });
         }
@@ -203,34 +197,29 @@ pub fn filter_left_parts(wordinfos: Vec<WordInfo>) -> Vec<WordInfo> {
     filtered_wordinfos
 }
 
-pub fn generate_right_parts(
+fn generate_right_parts(
     i: usize,
     j: usize,
     grid: &Grid,
+    gaddag: &Gaddag,
     wordinfos: Vec<WordInfo>,
 ) -> Vec<WordInfo> {
     let mut current_wordinfos = wordinfos;
     let mut next_wordinfos = Vec::new();
     let mut all_results = Vec::new();
     let mut y = j + 1;
-    while y < GRID_SIZE {
+    while y < grid.size() {
         if let Square::Letter(letter) = grid.squares[i][y] {
             // Si la case suivante contient une lettre on essaie de l'ajouter à chaque WordInfo
             for wordinfo in current_wordinfos {
-                if let Some(next_node) = wordinfo
-                    .node
-                    .borrow()
-                    .children
-                    .get(&letter.to_ascii_uppercase())
-                {
+                if let Some(next_node) = gaddag.child(wordinfo.node, letter.tile) {
                     let mut new_prefix = wordinfo.prefix.clone();
                     new_prefix.push(letter);
-                    let new_flat_score =
-                        wordinfo.score.0 + *LETTERS_VALUE.get(&letter).unwrap_or(&0);
+                    let new_flat_score = wordinfo.score.0 + grid.rules().letter_value(letter);
                     next_wordinfos.push(WordInfo {
                         prefix: new_prefix,
                         score: (new_flat_score, wordinfo.score.1, wordinfo.score.2),
-                        node: Rc::clone(next_node),
+                        node: next_node,
                         ..wordinfo
                     });
                 }
@@ -238,7 +227,7 @@ pub fn generate_right_parts(
         } else {
             // Si la case suivante ne possède pas de lettre on applique step à chaque WordInfo
             for wordinfo in current_wordinfos.drain(..) {
-                let results = step(i, y, grid, &wordinfo);
+                let results = step(i, y, grid, gaddag, &wordinfo);
                 for result in results {
                     next_wordinfos.push(result);
                 }
@@ -252,53 +241,238 @@ pub fn generate_right_parts(
     all_results
 }
 
-pub fn filter_valid_words(wordinfos: Vec<WordInfo>, direction: bool) -> Vec<ValidWord> {
-    // Ne retourne que les WordInfo qui sont des mots valides, et calcule leur score
+fn filter_valid_words(
+    i: usize,
+    j: usize,
+    grid: &Grid,
+    gaddag: &Gaddag,
+    wordinfos: Vec<WordInfo>,
+    direction: Direction,
+) -> Vec<WordInfo> {
+    // Ne retourne que les WordInfo qui sont des mots valides
     wordinfos
         .into_iter()
-        .filter(|wi| wi.node.borrow().is_word)
-        .map(|wi| {
-            let bonus = *BINGOS_BONUS.get(&wi.letters_nb).unwrap_or(&0);
-            let final_score = wi.score.0 * wi.score.1 + wi.score.2 + bonus;
-            ValidWord {
-                position: Grid::pos_to_ref(wi.position, direction),
-                rack: wi.rack,
-                word: wi.prefix,
-                score: final_score,
-            }
+        .filter(|wi| gaddag.is_word(wi.node))
+        // Le mot principal a au moins deux lettres, même si le dictionnaire en a d'une seule
+        .filter(|wi| wi.prefix.len() > 1)
+        // Le premier jeton posé est toujours sur l'ancre : un coup d'une seule lettre qui a des
+        // voisines horizontales forme aussi un mot horizontal et a déjà été trouvé dans ce sens
+        .filter(|wi| {
+            direction == Direction::Horizontal
+                || wi.letters_nb > 1
+                || grid.crosswords[i][j].is_none()
         })
         .collect()
 }
 
-pub fn generate_solutions(
+fn to_move(grid: &Grid, wordinfo: WordInfo, direction: Direction) -> Move {
+    // Construit le coup correspondant à un mot valide et calcule son score
+    // La grille est transposée pour un coup vertical : la ligne i y est la colonne i
+    let (i, start) = wordinfo.position;
+    let placed = wordinfo
+        .prefix
+        .iter()
+        .enumerate()
+        .filter(|&(k, _)| grid.is_empty(i, start + k))
+        .map(|(k, letter)| {
+            let (row, col) = match direction {
+                Direction::Horizontal => (i, start + k),
+                Direction::Vertical => (start + k, i),
+            };
+            PlacedTile {
+                row,
+                col,
+                letter: letter.tile,
+                blank: letter.blank,
+            }
+        })
+        .collect();
+    let (row, col) = match direction {
+        Direction::Horizontal => (i, start),
+        Direction::Vertical => (start, i),
+    };
+    let bonus = grid.rules().bingo_bonus(wordinfo.letters_nb as usize);
+    Move {
+        row,
+        col,
+        direction,
+        word: grid.rules().word_text(&wordinfo.prefix),
+        placed,
+        score: Score {
+            main: wordinfo.score.0 * wordinfo.score.1,
+            cross: wordinfo.score.2,
+            bingo: bonus,
+        },
+        leave: wordinfo.rack,
+    }
+}
+
+fn generate_anchor(
+    i: usize,
+    j: usize,
     grid: &Grid,
-    rack: &HashMap<char, usize>,
-    gaddag: &GaddagNode,
-) -> Vec<ValidWord> {
-    // Renvoie toutes les solutions jouables sur la grille
+    rack: &HashMap<Tile, usize>,
+    gaddag: &Gaddag,
+    direction: Direction,
+) -> Vec<WordInfo> {
+    // Renvoie les mots dont (i, j) est l'ancre la plus à gauche : la partie gauche ne passe
+    // jamais sur une autre ancre, donc chaque placement n'est généré qu'une fois
+    let left_parts = generate_left_parts(i, j, grid, rack, gaddag);
+    let valid_left_parts = filter_left_parts(gaddag, left_parts);
+    let right_parts = generate_right_parts(i, j, grid, gaddag, valid_left_parts);
+    filter_valid_words(i, j, grid, gaddag, right_parts, direction)
+}
+
+fn generate_row(
+    i: usize,
+    grid: &Grid,
+    rack: &HashMap<Tile, usize>,
+    gaddag: &Gaddag,
+    direction: Direction,
+) -> Vec<Move> {
+    // Renvoie les solutions ancrées sur la ligne i de la grille (transposée si direction est faux)
     let mut valid_words = Vec::new();
-    for i in 0..GRID_SIZE {
-        for j in 0..GRID_SIZE {
-            if grid.anchors[i][j] {
-                let left_parts = generate_left_parts(i, j, grid, rack, gaddag);
-                let valid_left_parts = filter_left_parts(left_parts);
-                let right_parts = generate_right_parts(i, j, grid, valid_left_parts);
-                let valid_right_parts = filter_valid_words(right_parts, true);
-                valid_words.extend(valid_right_parts);
-            }
+    for j in 0..grid.size() {
+        if grid.anchors[i][j] {
+            let wordinfos = generate_anchor(i, j, grid, rack, gaddag, direction);
+            valid_words.extend(wordinfos.into_iter().map(|wi| to_move(grid, wi, direction)));
         }
     }
+    valid_words
+}
+
+fn count_row(
+    i: usize,
+    grid: &Grid,
+    rack: &HashMap<Tile, usize>,
+    gaddag: &Gaddag,
+    direction: Direction,
+) -> usize {
+    // Compte les solutions ancrées sur la ligne i sans construire les ValidWord
+    (0..grid.size())
+        .filter(|&j| grid.anchors[i][j])
+        .map(|j| generate_anchor(i, j, grid, rack, gaddag, direction).len())
+        .sum()
+}
+pub fn generate_solutions(grid: &Grid, rack: &HashMap<Tile, usize>, gaddag: &Gaddag) -> Vec<Move> {
+    // Renvoie toutes les solutions jouables sur la grille
+    let mut valid_words = Vec::new();
+    for i in 0..grid.size() {
+        valid_words.extend(generate_row(i, grid, rack, gaddag, Direction::Horizontal));
+    }
     let transposed_grid = Grid::transpose_grid(grid, gaddag);
-    for i in 0..GRID_SIZE {
-        for j in 0..GRID_SIZE {
-            if transposed_grid.anchors[i][j] {
-                let left_parts = generate_left_parts(i, j, &transposed_grid, rack, gaddag);
-                let valid_left_parts = filter_left_parts(left_parts);
-                let right_parts = generate_right_parts(i, j, &transposed_grid, valid_left_parts);
-                let valid_right_parts = filter_valid_words(right_parts, false);
-                valid_words.extend(valid_right_parts);
-            }
-        }
+    for i in 0..grid.size() {
+        valid_words.extend(generate_row(
+            i,
+            &transposed_grid,
+            rack,
+            gaddag,
+            Direction::Vertical,
+        ));
     }
     valid_words
 }
+
+pub fn generate_solutions_parallel(
+    grid: &Grid,
+    rack: &HashMap<Tile, usize>,
+    gaddag: &Gaddag,
+) -> Vec<Move> {
+    // Comme generate_solutions, mais chaque ligne et chaque colonne est traitée sur un coeur
+    let transposed_grid = Grid::transpose_grid(grid, gaddag);
+    let lines: Vec<(&Grid, usize, Direction)> = (0..grid.size())
+        .map(|i| (grid, i, Direction::Horizontal))
+        .chain((0..grid.size()).map(|i| (&transposed_grid, i, Direction::Vertical)))
+        .collect();
+    lines
+        .into_par_iter()
+        .flat_map_iter(|(grid, i, direction)| generate_row(i, grid, rack, gaddag, direction))
+        .collect()
+}
+
+pub fn count_solutions(grid: &Grid, rack: &HashMap<Tile, usize>, gaddag: &Gaddag) -> usize {
+    // Renvoie le nombre de coups légaux distincts, chaque placement étant compté une fois
+    let transposed_grid = Grid::transpose_grid(grid, gaddag);
+    let lines: Vec<(&Grid, usize, Direction)> = (0..grid.size())
+        .map(|i| (grid, i, Direction::Horizontal))
+        .chain((0..grid.size()).map(|i| (&transposed_grid, i, Direction::Vertical)))
+        .collect();
+    lines
+        .into_par_iter()
+        .map(|(grid, i, direction)| count_row(i, grid, rack, gaddag, direction))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::RuleSet;
+    use std::collections::HashSet;
+
+    fn test_gaddag() -> Gaddag {
+        // Petit dictionnaire qui contient aussi des mots d'une seule lettre
+        let words = [
+            "A", "Y", "AS", "SA", "MA", "MAS", "SAMA", "AMAS", "TAS", "RAS", "ART",
+        ];
+        Gaddag::from_words(&words, "test", &RuleSet::french())
+    }
+
+    fn rack(letters: &str) -> HashMap<Tile, usize> {
+        RuleSet::french().parse_rack(letters).unwrap()
+    }
+
+    fn test_grid(gaddag: &Gaddag) -> Grid {
+        // Grille avec SAMA en 8H et MAS en K7
+        let mut grid = Grid::new();
+        grid.generate_grid();
+        grid.play(&Move::new(7, 7, Direction::Horizontal, "SAMA"), gaddag)
+            .unwrap();
+        grid.play(&Move::new(6, 10, Direction::Vertical, "MAS"), gaddag)
+            .unwrap();
+        grid
+    }
+
+    #[test]
+    fn each_placement_is_emitted_once() {
+        let gaddag = test_gaddag();
+        let grid = test_grid(&gaddag);
+        let solutions = generate_solutions(&grid, &rack("ARST?"), &gaddag);
+        assert!(!solutions.is_empty());
+        let mut seen = HashSet::new();
+        for solution in &solutions {
+            assert!(
+                seen.insert((solution.coordinates(), solution.word.clone())),
+                "{} {} emitted twice",
+                solution.coordinates(),
+                solution.word
+            );
+        }
+    }
+
+    #[test]
+    fn count_matches_generated_solutions() {
+        let gaddag = test_gaddag();
+        let grid = test_grid(&gaddag);
+        for letters in ["ARST?", "AM", "Y", "??"] {
+            let rack = rack(letters);
+            let solutions = generate_solutions(&grid, &rack, &gaddag);
+            assert_eq!(count_solutions(&grid, &rack, &gaddag), solutions.len());
+            assert_eq!(
+                generate_solutions_parallel(&grid, &rack, &gaddag).len(),
+                solutions.len()
+            );
+        }
+    }
+
+    #[test]
+    fn main_word_has_at_least_two_letters() {
+        let gaddag = test_gaddag();
+        let mut grid = Grid::new();
+        grid.generate_grid();
+        let solutions = generate_solutions(&grid, &rack("AY"), &gaddag);
+        assert!(solutions.iter().all(|s| s.word.chars().count() > 1));
+        let grid = test_grid(&gaddag);
+        let solutions = generate_solutions(&grid, &rack("AY"), &gaddag);
+        assert!(solutions.iter().all(|s| s.word.chars().count() > 1));
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use crate::grid::{Grid, Square};
+use crate::rack::rack_tiles;
+use crate::rules::{RuleSet, Tile, BLANK};
+
+// Lettre vue plus souvent que sa distribution ne le permet, sur la grille et le rack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Excess {
+    pub tile: Tile,
+    pub seen: usize,
+    pub count: usize,
+}
+
+// Jetons invisibles d'une position : ceux du sac et des racks adverses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unseen {
+    pub tiles: HashMap<Tile, usize>,
+    // Voyelles et consonnes invisibles, une lettre pouvant compter dans les deux comme le Y
+    pub vowels: usize,
+    pub consonants: usize,
+    // Lettres en trop, qui rendent la position impossible
+    pub excess: Vec<Excess>,
+}
+
+impl Unseen {
+    pub fn len(&self) -> usize {
+        self.tiles.values().sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn blanks(&self) -> usize {
+        self.tiles.get(&BLANK).copied().unwrap_or(0)
+    }
+
+    pub fn is_possible(&self) -> bool {
+        self.excess.is_empty()
+    }
+
+    pub fn to_vec(&self) -> Vec<Tile> {
+        // Liste des jetons invisibles dans un ordre fixe, le joker en dernier
+        rack_tiles(&self.tiles)
+    }
+}
+
+pub fn unseen_tiles(grid: &Grid, rack: &HashMap<Tile, usize>) -> Unseen {
+    // Retire de la distribution des règles les jetons de la grille, un joker posé comptant
+    // comme un joker, puis ceux du rack
+    let rules: &RuleSet = grid.rules();
+    let mut seen: HashMap<Tile, usize> = rack.clone();
+    for square in grid.squares.iter().flatten() {
+        if let Square::Letter(letter) = square {
+            let tile = if letter.blank { BLANK } else { letter.tile };
+            *seen.entry(tile).or_insert(0) += 1;
+        }
+    }
+    let mut tiles = HashMap::new();
+    let mut vowels = 0;
+    let mut consonants = 0;
+    for tile in rules.letters().chain([BLANK]) {
+        let left = rules
+            .count(tile)
+            .saturating_sub(seen.get(&tile).copied().unwrap_or(0));
+        if left == 0 {
+            continue;
+        }
+        tiles.insert(tile, left);
+        if rules.is_vowel(tile) {
+            vowels += left;
+        }
+        if rules.is_consonant(tile) {
+            consonants += left;
+        }
+    }
+    // Une lettre vue plus de fois qu'il n'y a de jetons trahit une erreur de saisie
+    let mut excess: Vec<Excess> = seen
+        .into_iter()
+        .filter(|&(tile, seen)| seen > rules.count(tile))
+        .map(|(tile, seen)| Excess {
+            tile,
+            seen,
+            count: rules.count(tile),
+        })
+        .collect();
+    excess.sort_unstable_by_key(|excess| excess.tile);
+    Unseen {
+        tiles,
+        vowels,
+        consonants,
+        excess,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gaddag::Gaddag;
+    use crate::moves::{Direction, Move};
+
+    fn test_grid() -> Grid {
+        // SAMA en H8, le premier A étant un joker
+        let gaddag = Gaddag::from_words(&["SAMA"], "test", &RuleSet::french());
+        let mut grid = Grid::new();
+        grid.generate_grid();
+        grid.play(&Move::new(7, 7, Direction::Horizontal, "SaMA"), &gaddag)
+            .unwrap();
+        grid
+    }
+
+    #[test]
+    fn full_bag_on_an_empty_board() {
+        let mut grid = Grid::new();
+        grid.generate_grid();
+        let unseen = unseen_tiles(&grid, &HashMap::new());
+        assert_eq!(unseen.len(), 102);
+        assert_eq!(unseen.blanks(), 2);
+        // Le Y et les jokers comptent à la fois comme voyelles et comme consonnes
+        assert_eq!(unseen.vowels, 47);
+        assert_eq!(unseen.consonants, 58);
+        assert!(unseen.is_possible());
+        let tiles = unseen.to_vec();
+        assert_eq!(tiles.len(), 102);
+        assert!(tiles.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(tiles[100..], [BLANK, BLANK]);
+    }
+
+    #[test]
+    fn board_and_rack_tiles_are_removed() {
+        let rules = RuleSet::french();
+        let rack = rules.parse_rack("AAZ?").unwrap();
+        let unseen = unseen_tiles(&test_grid(), &rack);
+        let tile = |name: &str| rules.tile(name).unwrap();
+        assert_eq!(unseen.len(), 94);
+        assert_eq!(unseen.blanks(), 0);
+        assert_eq!(unseen.tiles[&tile("A")], 6);
+        assert_eq!(unseen.tiles[&tile("S")], 5);
+        assert!(!unseen.tiles.contains_key(&tile("Z")));
+        assert_eq!(unseen.vowels, 42);
+        assert_eq!(unseen.consonants, 53);
+        assert!(unseen.is_possible());
+    }
+
+    #[test]
+    fn too_many_tiles_make_the_position_impossible() {
+        let rules = RuleSet::french();
+        let rack = rules.parse_rack("ZZ??").unwrap();
+        let unseen = unseen_tiles(&test_grid(), &rack);
+        assert_eq!(
+            unseen.excess,
+            [
+                Excess {
+                    tile: rules.tile("Z").unwrap(),
+                    seen: 2,
+                    count: 1,
+                },
+                Excess {
+                    tile: BLANK,
+                    seen: 3,
+                    count: 2,
+                },
+            ]
+        );
+        assert!(!unseen.is_possible());
+        assert_eq!(unseen.blanks(), 0);
+        assert!(!unseen.tiles.contains_key(&rules.tile("Z").unwrap()));
+    }
+}